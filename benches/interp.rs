@@ -0,0 +1,67 @@
+//! Times interpolation of literal-only text, a single template, and deeply nested templates.
+//!
+//! Run with `cargo bench`, optionally passing a filter to run only matching benchmarks.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use text_interpolator::TextInterpolator;
+
+/// Runs f repeatedly for about a second and prints the mean time per run.
+fn bench(name: &str, mut f: impl FnMut()) {
+    if std::env::args()
+        .skip(1)
+        .any(|filter| !filter.starts_with('-') && !name.contains(&filter))
+    {
+        return;
+    }
+
+    // Warm up, and find how many runs fit in a tenth of a second
+    let mut runs: u32 = 1;
+    loop {
+        let start = Instant::now();
+        (0..runs).for_each(|_| f());
+        if start.elapsed() > Duration::from_millis(100) {
+            break;
+        }
+        runs *= 2;
+    }
+
+    let start = Instant::now();
+    let mut total: u32 = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        (0..runs).for_each(|_| f());
+        total += runs;
+    }
+
+    println!("{name:<24} {:>12.1?}/iter", start.elapsed() / total);
+}
+
+fn map(template: &str) -> Option<&'static str> {
+    match template {
+        "name" => Some("world"),
+        "deep0" => Some("'deep1 'deep1"),
+        "deep1" => Some("'deep2 'deep2"),
+        "deep2" => Some("'deep3 'deep3"),
+        "deep3" => Some("'deep4 'deep4"),
+        "deep4" => Some("'deep5 'deep5"),
+        "deep5" => Some("leaf"),
+        _ => None,
+    }
+}
+
+fn main() {
+    let mut text_interpolator = TextInterpolator::default();
+    let literal = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(100);
+    let single = format!("{literal}Hello 'name!");
+
+    bench("literal", || {
+        black_box(text_interpolator.interp(black_box(&literal), &map).unwrap());
+    });
+    bench("single_template", || {
+        black_box(text_interpolator.interp(black_box(&single), &map).unwrap());
+    });
+    bench("nested", || {
+        black_box(text_interpolator.interp(black_box("'deep0"), &map).unwrap());
+    });
+}
@@ -0,0 +1,470 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+
+use crate::{defaults, TextInterpolator, UnresolvedAction};
+
+/// Template syntax recognized by a built TextInterpolator.
+#[derive(Debug, Clone)]
+enum Syntax {
+    Marker(char),
+    Braces,
+    Delimiters(String, Option<String>),
+}
+
+/// Builds a [`TextInterpolator`] from chainable configuration options.
+///
+/// Every option defaults to the behavior of [`TextInterpolator::default`].
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::TextInterpolatorBuilder;
+///
+/// let mut text_interpolator = TextInterpolatorBuilder::default()
+///     .marker('$')
+///     .max_depth(32)
+///     .strict(true)
+///     .seed(7)
+///     .build();
+///
+/// let output = text_interpolator.interp("Hello $name", &|template| match template {
+///     "name" => Some("world"),
+///     _ => None,
+/// });
+///
+/// assert_eq!("Hello world", output.unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TextInterpolatorBuilder {
+    syntax: Syntax,
+    max_depth: usize,
+    max_output_len: Option<usize>,
+    strict: bool,
+    known_templates: BTreeSet<String>,
+    aliases: BTreeMap<String, String>,
+    reject_unknown: bool,
+    recursive: bool,
+    detect_loops: bool,
+    literal_by_default: bool,
+    memoize: bool,
+    on_unresolved: UnresolvedAction,
+    sticky: bool,
+    trim_substitutions: bool,
+    capacity_hint: usize,
+    case_directives: bool,
+    case_insensitive_lookup: bool,
+    seed: Option<u64>,
+}
+
+impl Default for TextInterpolatorBuilder {
+    fn default() -> Self {
+        TextInterpolatorBuilder {
+            syntax: Syntax::Marker(defaults::DEFAULT_MARKER),
+            max_depth: defaults::MAX_DEPTH,
+            max_output_len: None,
+            strict: false,
+            known_templates: BTreeSet::new(),
+            aliases: BTreeMap::new(),
+            reject_unknown: false,
+            recursive: true,
+            detect_loops: true,
+            literal_by_default: false,
+            memoize: false,
+            on_unresolved: UnresolvedAction::Keep,
+            sticky: false,
+            trim_substitutions: false,
+            capacity_hint: 1,
+            case_directives: true,
+            case_insensitive_lookup: false,
+            seed: None,
+        }
+    }
+}
+
+impl TextInterpolatorBuilder {
+    /// Uses marker as the template sigil, see [`defaults::with_marker`].
+    pub fn marker(mut self, marker: char) -> Self {
+        self.syntax = Syntax::Marker(marker);
+        self
+    }
+
+    /// Uses brace-delimited templates, see [`defaults::with_braces`].
+    pub fn braces(mut self) -> Self {
+        self.syntax = Syntax::Braces;
+        self
+    }
+
+    /// Uses templates that start with prefix and, when given, end with suffix, see
+    /// [`defaults::with_delimiters`].
+    pub fn delimiters(mut self, prefix: &str, suffix: Option<&str>) -> Self {
+        self.syntax = Syntax::Delimiters(prefix.into(), suffix.map(String::from));
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits the length of the output, see [`TextInterpolator::max_output_len`].
+    pub fn max_output_len(mut self, limit: usize) -> Self {
+        self.max_output_len = Some(limit);
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Adds names to the known templates, see [`TextInterpolator::register_templates`].
+    pub fn register_templates(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.known_templates.extend(names);
+        self
+    }
+
+    /// Looks up template from under the name to, see [`TextInterpolator::add_alias`].
+    pub fn alias(mut self, from: &str, to: &str) -> Self {
+        self.aliases.insert(from.into(), to.into());
+        self
+    }
+
+    /// Adds every alias from one name to another, see [`TextInterpolator::add_alias`].
+    pub fn aliases(mut self, aliases: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.aliases.extend(aliases);
+        self
+    }
+
+    /// Fails on templates that aren't registered, see [`TextInterpolator::reject_unknown`].
+    pub fn reject_unknown(mut self, reject_unknown: bool) -> Self {
+        self.reject_unknown = reject_unknown;
+        self
+    }
+
+    /// Writes substitutions verbatim when false, see [`TextInterpolator::recursive`].
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Lets templates re-enter themselves when false, see [`TextInterpolator::detect_loops`].
+    pub fn detect_loops(mut self, detect_loops: bool) -> Self {
+        self.detect_loops = detect_loops;
+        self
+    }
+
+    /// Writes substitutions from the map verbatim unless they are marked as templates, see
+    /// [`TextInterpolator::literal_by_default`].
+    pub fn literal_by_default(mut self, literal: bool) -> Self {
+        self.literal_by_default = literal;
+        self
+    }
+
+    /// Reuses the resolved substitution of repeated templates, see
+    /// [`TextInterpolator::memoize`].
+    pub fn memoize(mut self, memoize: bool) -> Self {
+        self.memoize = memoize;
+        self
+    }
+
+    /// Sets what to write in place of templates without a substitution, see
+    /// [`UnresolvedAction`].
+    pub fn on_unresolved(mut self, action: UnresolvedAction) -> Self {
+        self.on_unresolved = action;
+        self
+    }
+
+    /// Reuses the first substitute picked for a template, see [`TextInterpolator::sticky`].
+    pub fn sticky(mut self, sticky: bool) -> Self {
+        self.sticky = sticky;
+        self
+    }
+
+    /// Normalizes the whitespace of substitutions, see
+    /// [`TextInterpolator::trim_substitutions`].
+    pub fn trim_substitutions(mut self, trim: bool) -> Self {
+        self.trim_substitutions = trim;
+        self
+    }
+
+    /// Sets the expected ratio of output length to input length, see
+    /// [`TextInterpolator::capacity_hint`].
+    pub fn capacity_hint(mut self, factor: usize) -> Self {
+        self.capacity_hint = factor;
+        self
+    }
+
+    /// Lets the case of a template name carry over to its substitution, see
+    /// [`TextInterpolator::case_directives`].
+    pub fn case_directives(mut self, case_directives: bool) -> Self {
+        self.case_directives = case_directives;
+        self
+    }
+
+    /// Looks templates up by their lowercase name, see
+    /// [`TextInterpolator::case_insensitive_lookup`].
+    pub fn case_insensitive_lookup(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive_lookup = case_insensitive;
+        self
+    }
+
+    /// Seeds the random number generator, see [`TextInterpolator::seed`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(self) -> TextInterpolator {
+        let mut text_interpolator = match self.syntax {
+            Syntax::Marker(defaults::DEFAULT_MARKER) => TextInterpolator::default(),
+            Syntax::Marker(marker) => defaults::with_marker(marker),
+            Syntax::Braces => defaults::with_braces(),
+            Syntax::Delimiters(prefix, suffix) => {
+                defaults::with_delimiters(&prefix, suffix.as_deref())
+            }
+        };
+
+        text_interpolator.max_depth = self.max_depth;
+        text_interpolator.max_output_len = self.max_output_len;
+        text_interpolator.strict = self.strict;
+        text_interpolator.known_templates = self.known_templates;
+        text_interpolator.aliases = self.aliases;
+        text_interpolator.reject_unknown = self.reject_unknown;
+        text_interpolator.recursive = self.recursive;
+        text_interpolator.detect_loops = self.detect_loops;
+        text_interpolator.literal_by_default = self.literal_by_default;
+        text_interpolator.memoize = self.memoize;
+        text_interpolator.on_unresolved = self.on_unresolved;
+        text_interpolator.sticky = self.sticky;
+        text_interpolator.trim_substitutions = self.trim_substitutions;
+        text_interpolator.capacity_hint = self.capacity_hint;
+        text_interpolator.case_directives = self.case_directives;
+        text_interpolator.case_insensitive_lookup = self.case_insensitive_lookup;
+
+        if let Some(seed) = self.seed {
+            text_interpolator.seed(seed);
+        }
+
+        text_interpolator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InterpError;
+
+    fn map_template(template: &str) -> Option<&'static str> {
+        match template {
+            "sentence" => Some("A 'noun {noun} $noun."),
+            "noun" => Some("place"),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn default_builder_matches_default_interpolator() {
+        let mut built = TextInterpolatorBuilder::default().build();
+        let mut default = TextInterpolator::default();
+
+        assert_eq!(default.max_depth, built.max_depth);
+        assert_eq!(default.strict, built.strict);
+        assert_eq!(
+            default.interp("'sentence ''tis", &map_template).unwrap(),
+            built.interp("'sentence ''tis", &map_template).unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_uses_braces() {
+        let mut text_interpolator = TextInterpolatorBuilder::default().braces().build();
+
+        assert_eq!(
+            "place 'noun $noun",
+            text_interpolator
+                .interp("{noun} 'noun $noun", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_uses_marker() {
+        let mut text_interpolator = TextInterpolatorBuilder::default().marker('$').build();
+
+        assert_eq!(
+            "'noun {noun} place",
+            text_interpolator
+                .interp("'noun {noun} $noun", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_uses_delimiters() {
+        let mut text_interpolator = TextInterpolatorBuilder::default()
+            .delimiters("${", Some("}"))
+            .build();
+
+        assert_eq!(
+            "place {noun} $noun",
+            text_interpolator
+                .interp("${noun} {noun} $noun", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_sets_max_depth() {
+        let mut text_interpolator = TextInterpolatorBuilder::default().max_depth(1).build();
+
+        assert!(matches!(
+            text_interpolator.interp("'sentence", &map_template),
+            Err(InterpError::MaxDepthExceeded { depth: 2 })
+        ));
+    }
+
+    #[test]
+    fn builder_sets_strict() {
+        let mut text_interpolator = TextInterpolatorBuilder::default().strict(true).build();
+
+        assert!(matches!(
+            text_interpolator.interp("'missing", &map_template),
+            Err(InterpError::UndefinedTemplate { .. })
+        ));
+    }
+
+    #[test]
+    fn builder_sets_max_output_len() {
+        let mut text_interpolator = TextInterpolatorBuilder::default().max_output_len(8).build();
+
+        assert!(matches!(
+            text_interpolator.interp("'sentence", &map_template),
+            Err(InterpError::OutputTooLarge { limit: 8 })
+        ));
+    }
+
+    #[test]
+    fn builder_rejects_unknown_templates() {
+        let mut text_interpolator = TextInterpolatorBuilder::default()
+            .register_templates(["noun".to_string()])
+            .reject_unknown(true)
+            .build();
+
+        assert!(matches!(
+            text_interpolator.interp("'noun 'sentence", &map_template),
+            Err(InterpError::UnknownTemplate { template, .. }) if template == "sentence"
+        ));
+    }
+
+    #[test]
+    fn builder_sets_literal_by_default() {
+        let mut text_interpolator = TextInterpolatorBuilder::default()
+            .literal_by_default(true)
+            .build();
+
+        assert_eq!(
+            "A 'noun {noun} $noun.",
+            text_interpolator
+                .interp("'sentence", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_sets_case_directives() {
+        let mut text_interpolator = TextInterpolatorBuilder::default()
+            .case_directives(false)
+            .build();
+
+        assert_eq!(
+            "place 'Noun",
+            text_interpolator
+                .interp("'noun 'Noun", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_sets_case_insensitive_lookup() {
+        let mut text_interpolator = TextInterpolatorBuilder::default()
+            .case_insensitive_lookup(true)
+            .case_directives(false)
+            .build();
+
+        assert_eq!(
+            "place place",
+            text_interpolator
+                .interp("'noun 'NOUN", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_sets_on_unresolved() {
+        let mut text_interpolator = TextInterpolatorBuilder::default()
+            .on_unresolved(UnresolvedAction::StripMarker)
+            .build();
+
+        assert_eq!(
+            "place missing",
+            text_interpolator
+                .interp("'noun 'missing", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_sets_capacity_hint() {
+        let text_interpolator = TextInterpolatorBuilder::default().capacity_hint(4).build();
+
+        assert_eq!(4, text_interpolator.capacity_hint);
+    }
+
+    #[test]
+    fn builder_sets_trim_substitutions() {
+        let text_interpolator = TextInterpolatorBuilder::default()
+            .trim_substitutions(true)
+            .build();
+
+        assert!(text_interpolator.trim_substitutions);
+    }
+
+    #[test]
+    fn builder_sets_memoize() {
+        let text_interpolator = TextInterpolatorBuilder::default().memoize(true).build();
+
+        assert!(text_interpolator.memoize);
+    }
+
+    #[test]
+    fn builder_adds_aliases() {
+        let mut text_interpolator = TextInterpolatorBuilder::default()
+            .alias("thing", "noun")
+            .aliases([("place".to_string(), "noun".to_string())])
+            .build();
+
+        assert_eq!(
+            "place place place",
+            text_interpolator
+                .interp("'noun 'thing 'place", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_seed_is_reproducible() {
+        let map = |template: &str| match template {
+            "digit" => Some((0..10).map(|digit| digit.to_string()).collect()),
+            _ => None,
+        };
+        let text = "'digit 'digit 'digit 'digit 'digit 'digit";
+
+        let mut a = TextInterpolatorBuilder::default().seed(5).build();
+        let mut b = TextInterpolatorBuilder::default().seed(5).build();
+
+        assert_eq!(
+            a.interp_random(text, &map).unwrap(),
+            b.interp_random(text, &map).unwrap()
+        );
+    }
+}
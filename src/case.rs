@@ -0,0 +1,79 @@
+//! Capitalization of substitutions that stays correct beyond ASCII.
+//!
+//! Capitalizing is not the same as uppercasing the first character: a letter whose uppercase
+//! form is several characters, such as `ß`, should only have the first of them uppercased, and
+//! digraphs such as `ǆ` have a separate titlecase form. Combining marks after the first letter
+//! belong to the same grapheme and are kept as they are.
+
+use alloc::string::String;
+
+/// Capitalizes the first letter of text, skipping any punctuation before it, and leaves
+/// everything else unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::case::capitalize;
+///
+/// assert_eq!("Hello world", capitalize("hello world"));
+/// assert_eq!("¿Qué?", capitalize("¿qué?"));
+/// assert_eq!("Ssharp", capitalize("ßharp"));
+/// assert_eq!("ǅungla", capitalize("ǆungla"));
+/// assert_eq!("E\u{301}cole", capitalize("e\u{301}cole"));
+/// ```
+pub fn capitalize(text: &str) -> String {
+    let Some((index, first)) = text.char_indices().find(|(_, ch)| ch.is_alphabetic()) else {
+        return text.into();
+    };
+
+    let mut capitalized = String::with_capacity(text.len());
+    capitalized.push_str(&text[..index]);
+    push_titlecase(first, &mut capitalized);
+    capitalized.push_str(&text[index + first.len_utf8()..]);
+
+    capitalized
+}
+
+/// Pushes the titlecase form of a letter, which for most letters is their uppercase.
+fn push_titlecase(ch: char, output: &mut String) {
+    let digraph = match ch {
+        'Ǆ' | 'ǅ' | 'ǆ' => 'ǅ',
+        'Ǉ' | 'ǈ' | 'ǉ' => 'ǈ',
+        'Ǌ' | 'ǋ' | 'ǌ' => 'ǋ',
+        'Ǳ' | 'ǲ' | 'ǳ' => 'ǲ',
+        _ => {
+            // Only the first letter of a multi-letter uppercase form is capitalized, as in Ss
+            let mut upper = ch.to_uppercase();
+            output.extend(upper.next());
+            output.extend(upper.flat_map(char::to_lowercase));
+            return;
+        }
+    };
+
+    output.push(digraph);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capitalize_non_ascii_letters() {
+        for (text, capitalized) in [
+            ("", ""),
+            ("123", "123"),
+            ("'émigré", "'Émigré"),
+            ("ﬁsh", "Fish"),
+            ("ǳemal", "ǲemal"),
+            ("Ǉubljana", "ǈubljana"),
+            ("\u{390}", "\u{399}\u{308}\u{301}"),
+            ("\u{1F0}", "J\u{30C}"),
+            ("և", "Եւ"),
+            ("o\u{308}l", "O\u{308}l"),
+            ("ıi", "Ii"),
+            ("名前", "名前"),
+        ] {
+            assert_eq!(capitalized, capitalize(text), "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::defaults::is_name_char;
+use crate::{
+    has_line_break, CallState, InterpError, Substitution, TemplateSplit, TextInterpolator,
+};
+
+/// Keyword that follows the prefix of a conditional, as in `'if(premium: Thanks!)`.
+const IF: &str = "if(";
+
+/// A conditional found in text.
+pub(crate) struct Conditional {
+    /// Byte range of the whole conditional, from its prefix to its suffix.
+    pub(crate) range: Range<usize>,
+    pub(crate) condition: Range<usize>,
+    pub(crate) body: Range<usize>,
+}
+
+/// Maps byte offsets of text whose conditionals have been expanded back to the text as written,
+/// so spans found in the expanded text can be reported against the input.
+#[derive(Debug, Default)]
+pub(crate) struct OffsetMap {
+    /// Start of every piece copied from the input, in the expanded text and in the input, in
+    /// order. Empty when nothing was expanded, so every offset maps to itself.
+    pieces: Vec<(usize, usize)>,
+}
+
+impl OffsetMap {
+    /// Maps a range of the expanded text to the range of the input it was copied from.
+    pub(crate) fn input_span(&self, span: Range<usize>) -> Range<usize> {
+        let start = self.input_offset(span.start);
+        // The end is mapped through the last byte of the range, since a word can end a piece
+        let end = match span.end > span.start {
+            true => self.input_offset(span.end - 1) + 1,
+            false => start,
+        };
+
+        start..end
+    }
+
+    fn input_offset(&self, offset: usize) -> usize {
+        let index = self
+            .pieces
+            .partition_point(|&(expanded, _)| expanded <= offset);
+
+        match index.checked_sub(1) {
+            Some(index) => {
+                let (expanded, input) = self.pieces[index];
+                input + offset - expanded
+            }
+            None => offset,
+        }
+    }
+
+    /// Copies a piece of the input starting at the given input offset to output.
+    fn copy(&mut self, output: &mut String, piece: &str, input: usize) {
+        if !piece.is_empty() {
+            self.pieces.push((output.len(), input));
+            output.push_str(piece);
+        }
+    }
+
+    /// Truncates output, forgetting the pieces that no longer start in it.
+    fn truncate(&mut self, output: &mut String, len: usize) {
+        output.truncate(len);
+        while self
+            .pieces
+            .last()
+            .is_some_and(|&(expanded, _)| expanded >= len)
+        {
+            self.pieces.pop();
+        }
+    }
+}
+
+impl TextInterpolator {
+    /// Replaces every conditional in text with its body if its condition holds, and removes it
+    /// otherwise, before the templates of the text are expanded.
+    ///
+    /// A conditional is written `'if(condition: body)` at the start of a word, between the
+    /// interpolator's conditional delimiters. The condition is a template name looked up in the
+    /// map, and holds when it resolves to a value that isn't empty, `false`, or `0` once
+    /// trimmed. Conditions that don't resolve don't hold, even in strict mode. A removed
+    /// conditional takes one neighboring run of separators with it, the same way an empty
+    /// substitution does.
+    pub(crate) fn expand_conditionals<'t, 'm, S: Into<Substitution<'m>>>(
+        &self,
+        text: Cow<'t, str>,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &CallState,
+        depth: usize,
+    ) -> Result<Cow<'t, str>, InterpError> {
+        self.expand_conditionals_mapped(text, map, state, depth, &mut OffsetMap::default())
+    }
+
+    /// Expands the conditionals of text the same way as
+    /// [`TextInterpolator::expand_conditionals`], recording in offsets where each piece of the
+    /// expanded text was copied from.
+    pub(crate) fn expand_conditionals_mapped<'t, 'm, S: Into<Substitution<'m>>>(
+        &self,
+        text: Cow<'t, str>,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &CallState,
+        depth: usize,
+        offsets: &mut OffsetMap,
+    ) -> Result<Cow<'t, str>, InterpError> {
+        if self.find_conditional(&text, 0).is_none() {
+            return Ok(text);
+        }
+
+        let mut output = String::with_capacity(text.len());
+        self.expand_into(&text, 0, map, state, depth, &mut output, offsets)?;
+
+        Ok(Cow::Owned(output))
+    }
+
+    /// Appends text with its conditionals expanded to output, where text starts at the given
+    /// offset of the input.
+    #[allow(clippy::too_many_arguments)]
+    fn expand_into<'m, S: Into<Substitution<'m>>>(
+        &self,
+        text: &str,
+        input_start: usize,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &CallState,
+        depth: usize,
+        output: &mut String,
+        offsets: &mut OffsetMap,
+    ) -> Result<(), InterpError> {
+        // Separators written before text are never taken by a conditional in it
+        let floor = output.len();
+        let mut copied = 0;
+
+        while let Some(conditional) = self.find_conditional(text, copied) {
+            let before = &text[copied..conditional.range.start];
+            offsets.copy(output, before, input_start + copied);
+            copied = conditional.range.end;
+
+            // Conditions in the input are reported where they are written, others at the word
+            // whose substitution they are in
+            let span = match depth {
+                1 => {
+                    input_start + conditional.condition.start
+                        ..input_start + conditional.condition.end
+                }
+                _ => state.word_span.clone(),
+            };
+            if self.holds(&text[conditional.condition], span, map, state, depth)? {
+                let body = &text[conditional.body.clone()];
+                let body_start = input_start + conditional.body.start;
+                self.expand_into(body, body_start, map, state, depth, output, offsets)?;
+                continue;
+            }
+
+            let separators = text[copied..]
+                .find(|ch| !(self.is_separator)(ch))
+                .unwrap_or(text.len() - copied);
+            let after = &text[copied..copied + separators];
+            let kept = output[floor..]
+                .trim_end_matches(|ch| (self.is_separator)(ch))
+                .len();
+            let before = &output[floor + kept..];
+
+            // A run holding a line break is never taken, the same as around empty words
+            if !after.is_empty() && !has_line_break(after) {
+                copied += separators;
+            } else if !has_line_break(before) {
+                offsets.truncate(output, floor + kept);
+            }
+        }
+        offsets.copy(output, &text[copied..], input_start + copied);
+
+        Ok(())
+    }
+
+    /// Whether text contains a conditional, so it has to be expanded before interpolating.
+    pub(crate) fn contains_conditional(&self, text: &str) -> bool {
+        self.find_conditional(text, 0).is_some()
+    }
+
+    /// Finds the templates of text without substituting anything, along with the byte range of
+    /// the word each is written in.
+    ///
+    /// The condition of a conditional counts as a template whose range is the condition itself,
+    /// and the templates of its body are found as if the condition held.
+    pub(crate) fn template_words<'a>(&self, text: &'a str) -> Vec<(&'a str, Range<usize>)> {
+        let mut words = Vec::new();
+        self.push_template_words(text, 0, &mut words);
+
+        words
+    }
+
+    /// Pushes the templates of text, which starts at the given offset of the input, to words.
+    fn push_template_words<'a>(
+        &self,
+        text: &'a str,
+        input_start: usize,
+        words: &mut Vec<(&'a str, Range<usize>)>,
+    ) {
+        let mut from = 0;
+
+        loop {
+            let conditional = self.find_conditional(text, from);
+            let end = conditional
+                .as_ref()
+                .map_or(text.len(), |conditional| conditional.range.start);
+
+            let mut offset = from;
+            for (is_word, item) in self.split_words(&text[from..end]) {
+                let word = &text[offset..offset + item.len()];
+                let template = (self.extract_template)(word).template;
+                if is_word && !template.is_empty() {
+                    words.push((
+                        template,
+                        input_start + offset..input_start + offset + word.len(),
+                    ));
+                }
+                offset += item.len();
+            }
+
+            let Some(conditional) = conditional else {
+                return;
+            };
+
+            let condition = conditional.condition;
+            words.push((
+                &text[condition.clone()],
+                input_start + condition.start..input_start + condition.end,
+            ));
+
+            let body = conditional.body;
+            self.push_template_words(&text[body.clone()], input_start + body.start, words);
+            from = conditional.range.end;
+        }
+    }
+
+    /// Byte ranges of the outermost conditionals of text. One whose closing parenthesis is
+    /// missing extends to the end, since the rest of a text read in parts may still close it.
+    #[cfg(feature = "std")]
+    pub(crate) fn conditional_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut from = 0;
+
+        while let Some((start, open)) = self.openers(text, from).next() {
+            let end = matching_parenthesis(&text[open..])
+                .map(|close| open + close + 1 + self.conditional_suffix().len())
+                .unwrap_or(text.len())
+                .min(text.len());
+
+            ranges.push(start..end);
+            from = end;
+        }
+
+        ranges
+    }
+
+    /// Finds the first conditional in text at or after from.
+    pub(crate) fn find_conditional(&self, text: &str, from: usize) -> Option<Conditional> {
+        self.openers(text, from).find_map(|(start, open)| {
+            let close = open + matching_parenthesis(&text[open..])?;
+            let suffix = self.conditional_suffix();
+            if !text[close + 1..].starts_with(suffix) {
+                return None;
+            }
+
+            let colon = open + text[open..close].find(':')?;
+            let condition = trimmed(text, open..colon);
+            if condition.is_empty() || !text[condition.clone()].chars().all(is_name_char) {
+                return None;
+            }
+
+            Some(Conditional {
+                range: start..close + 1 + suffix.len(),
+                condition,
+                body: trimmed(text, colon + 1..close),
+            })
+        })
+    }
+
+    /// Yields the start of every conditional opener at the start of a word of text at or after
+    /// from, along with the offset right after the opener.
+    fn openers<'a>(
+        &'a self,
+        text: &'a str,
+        from: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.conditional_delimiters
+            .iter()
+            .flat_map(move |(prefix, _)| {
+                text[from..]
+                    .match_indices(prefix.as_str())
+                    .map(move |(index, _)| (from + index, from + index + prefix.len()))
+            })
+            .filter(move |&(start, after_prefix)| {
+                text[after_prefix..].starts_with(IF)
+                    && text[..start]
+                        .chars()
+                        .next_back()
+                        .is_none_or(|ch| (self.is_separator)(ch))
+            })
+            .map(|(start, after_prefix)| (start, after_prefix + IF.len()))
+    }
+
+    /// Text closing a conditional after its closing parenthesis.
+    fn conditional_suffix(&self) -> &str {
+        self.conditional_delimiters
+            .as_ref()
+            .map_or("", |(_, suffix)| suffix.as_str())
+    }
+
+    /// Checks whether a condition resolves to a true value, subject to the same limits, allowed
+    /// templates, and registered templates as any other template of the call.
+    fn holds<'m, S: Into<Substitution<'m>>>(
+        &self,
+        condition: &str,
+        span: Range<usize>,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &CallState,
+        depth: usize,
+    ) -> Result<bool, InterpError> {
+        if !self.is_allowed(condition, state) {
+            return Ok(false);
+        }
+        if self.is_rejected(condition) {
+            return Err(InterpError::UnknownTemplate {
+                template: condition.into(),
+                span,
+            });
+        }
+
+        let template_split = TemplateSplit {
+            template: condition,
+            ..Default::default()
+        };
+        let resolved = self.resolve(&template_split, map, depth)?;
+
+        Ok(resolved
+            .substitute
+            .is_some_and(|value| !matches!(value.trim(), "" | "false" | "0")))
+    }
+}
+
+/// Narrows a range of text to exclude whitespace at either end.
+fn trimmed(text: &str, range: Range<usize>) -> Range<usize> {
+    let piece = &text[range.clone()];
+    let start = range.start + (piece.len() - piece.trim_start().len());
+
+    start..start + piece.trim().len()
+}
+
+/// Byte index of the parenthesis closing the one just before text, skipping nested pairs.
+fn matching_parenthesis(text: &str) -> Option<usize> {
+    let mut open = 0usize;
+
+    for (index, ch) in text.char_indices() {
+        match ch {
+            '(' => open += 1,
+            ')' if open == 0 => return Some(index),
+            ')' => open -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{defaults, InterpError};
+
+    fn map_template(template: &str) -> Option<&'static str> {
+        match template {
+            "premium" => Some("true"),
+            "trial" => Some(" 0 "),
+            "banned" => Some("false"),
+            "blank" => Some(""),
+            "name" => Some("Ada"),
+            "greeting" => {
+                Some("Hi 'if(premium: dear) 'name 'if(trial: (trial)). 'if(premium: Bye!)")
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn conditionals_include_their_body_only_when_true() {
+        let mut text_interpolator = TextInterpolator::default();
+
+        for (text, expected) in [
+            (
+                "'if(premium: Thanks for subscribing, 'name!) Bye.",
+                "Thanks for subscribing, Ada! Bye.",
+            ),
+            ("Hello 'if(banned: dear) 'name", "Hello Ada"),
+            ("'if(blank: Hello) 'name", "Ada"),
+            ("Hello 'name 'if(missing: again)", "Hello Ada"),
+            ("Hello 'if(trial: dear).", "Hello."),
+            ("'if(premium: 'if(banned: banned) (premium))", "(premium)"),
+            ("line1 'if(banned: x)\nline2", "line1\nline2"),
+            ("'if(banned: x)\nline2", "\nline2"),
+            ("line1\n'if(banned: x)", "line1\n"),
+            ("line1\n'if(banned: x)\nline2", "line1\n\nline2"),
+            ("'greeting", "Hi dear Ada. Bye!"),
+            (
+                "'if(premium) ''if(premium: x) 'if(: x)",
+                "'if(premium) 'if(premium: x) 'if(: x)",
+            ),
+            ("'if(premium: unclosed", "'if(premium: unclosed"),
+        ] {
+            assert_eq!(
+                expected,
+                text_interpolator.interp(text, &map_template).unwrap(),
+                "{text:?}"
+            );
+            assert_eq!(
+                expected,
+                text_interpolator.interp_cow(text, &map_template).unwrap(),
+                "{text:?}"
+            );
+        }
+
+        text_interpolator.strict = true;
+        assert_eq!(
+            "Hello",
+            text_interpolator
+                .interp("Hello 'if(missing: 'undefined)", &map_template)
+                .unwrap()
+        );
+        assert!(matches!(
+            text_interpolator.interp("'if(premium: 'undefined)", &map_template),
+            Err(InterpError::UndefinedTemplate { template, .. }) if template == "undefined"
+        ));
+    }
+
+    #[test]
+    fn errors_after_conditionals_locate_the_input_word() {
+        let mut text_interpolator = TextInterpolator {
+            strict: true,
+            ..Default::default()
+        };
+
+        for text in [
+            "'if(premium: hello there) 'missing",
+            "'if(banned: hello there) 'missing",
+            "Hi 'if(premium: 'if(trial: x) dear) ('missing).",
+            "'if(premium: hello 'missing)",
+        ] {
+            let Err(InterpError::UndefinedTemplate { template, span }) =
+                text_interpolator.interp(text, &map_template)
+            else {
+                panic!("expected an undefined template error for {text:?}");
+            };
+            assert_eq!("missing", template);
+            assert_eq!(
+                "'missing",
+                text[span].trim_matches(['(', ')', '.']),
+                "{text:?}"
+            );
+        }
+        assert!(matches!(
+            text_interpolator.interp("'if(premium: hello there) 'missing", &map_template),
+            Err(InterpError::UndefinedTemplate { span, .. }) if span == (26..34)
+        ));
+
+        text_interpolator.strict = false;
+        text_interpolator.reject_unknown = true;
+        text_interpolator.register_templates(["name".to_string(), "premium".to_string()]);
+        assert!(matches!(
+            text_interpolator.interp("'if(premium: 'name and) 'other", &map_template),
+            Err(InterpError::UnknownTemplate { template, span })
+                if template == "other" && span == (24..30)
+        ));
+    }
+
+    #[test]
+    fn conditionals_use_the_template_delimiters() {
+        for (mut text_interpolator, text, expected) in [
+            (
+                defaults::with_delimiters("%%", Some("%%")),
+                "%%if(premium: Hi %%name%%)%% %%if(banned: x)%% %if(premium: y)",
+                "Hi Ada %if(premium: y)",
+            ),
+            (
+                defaults::with_braces(),
+                "{if(premium: Hi {name}!)} {if(banned: x)} {if(premium: y) z}",
+                "Hi Ada! {if(premium: y) z}",
+            ),
+            (
+                defaults::with_delimiters("€€", None),
+                "€€if(premium: €€name) €€if(banned: x) 'if(premium: y)",
+                "Ada 'if(premium: y)",
+            ),
+            (
+                defaults::with_marker('$'),
+                "$if(premium: $name) $if(banned: x)",
+                "Ada",
+            ),
+        ] {
+            assert_eq!(
+                expected,
+                text_interpolator.interp(text, &map_template).unwrap(),
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn conditions_are_checked_like_templates() {
+        let mut text_interpolator = TextInterpolator {
+            reject_unknown: true,
+            ..Default::default()
+        };
+        text_interpolator.register_templates(["name".to_string()]);
+
+        assert!(matches!(
+            text_interpolator.interp("Hi 'if(premium: 'name)", &map_template),
+            Err(InterpError::UnknownTemplate { template, span })
+                if template == "premium" && span == (7..14)
+        ));
+        assert_eq!(
+            Err(vec![crate::ValidationIssue::UnknownTemplate(
+                "premium".to_string()
+            )]),
+            text_interpolator.validate("Hi 'if(premium: 'name)", &map_template)
+        );
+
+        let mut text_interpolator = TextInterpolator::default();
+        assert_eq!(
+            ("Hi".to_string(), 0),
+            text_interpolator
+                .interp_limited("Hi 'if(premium: 'name)", &map_template, 0)
+                .unwrap()
+        );
+        #[cfg(feature = "std")]
+        assert_eq!(
+            "Hi Ada",
+            text_interpolator
+                .interp_subset(
+                    "Hi 'if(premium: 'name) 'if(name: 'name)",
+                    &map_template,
+                    &["name".to_string()].into(),
+                )
+                .unwrap()
+        );
+    }
+}
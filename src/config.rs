@@ -0,0 +1,303 @@
+//! Plain settings for building a [`TextInterpolator`], which unlike the interpolator itself
+//! can be stored and loaded.
+//!
+//! A [`Config`] is written and parsed as `key = value` lines, one setting per line, so it can
+//! live in a config file:
+//!
+//! ```text
+//! # Template engine settings
+//! marker = braces
+//! max_depth = 32
+//! strict = true
+//! ```
+//!
+//! Blank lines and lines starting with `#` are ignored, and settings that are left out keep
+//! their default.
+//!
+//! The crate has no dependencies, so there is deliberately no `serde` feature deriving
+//! `Serialize` and `Deserialize` for these types. A config can still be stored in a format
+//! serde handles as a single string, written with [`fmt::Display`] and read back with
+//! [`FromStr`].
+
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{defaults, TextInterpolator, TextInterpolatorBuilder};
+
+/// Template syntax named by a [`Config`], standing in for an is_template and extract_template
+/// pair of [`defaults`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Marker {
+    /// Templates start with an apostrophe, as in `'name`.
+    #[default]
+    Apostrophe,
+    /// Templates start with the given character, see [`defaults::with_marker`].
+    Char(char),
+    /// Templates are enclosed in braces, as in `{name}`, see [`defaults::with_braces`].
+    Braces,
+    /// Templates start with prefix and, when given, end with suffix, as in `%%name%%`, see
+    /// [`defaults::with_delimiters`]. Neither can contain whitespace, which separates words.
+    Delimiters {
+        prefix: String,
+        suffix: Option<String>,
+    },
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Marker::Apostrophe => f.write_str("apostrophe"),
+            Marker::Char(marker) => write!(f, "{marker}"),
+            Marker::Braces => f.write_str("braces"),
+            Marker::Delimiters {
+                prefix,
+                suffix: None,
+            } => write!(f, "delimiters {prefix}"),
+            Marker::Delimiters {
+                prefix,
+                suffix: Some(suffix),
+            } => write!(f, "delimiters {prefix} {suffix}"),
+        }
+    }
+}
+
+impl FromStr for Marker {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut words = text.split_whitespace();
+        if words.next() == Some("delimiters") {
+            return match (words.next(), words.next(), words.next()) {
+                (Some(prefix), suffix, None) => Ok(Marker::Delimiters {
+                    prefix: prefix.to_string(),
+                    suffix: suffix.map(str::to_string),
+                }),
+                _ => Err(()),
+            };
+        }
+
+        let mut chars = text.chars();
+
+        match (text, chars.next(), chars.next()) {
+            ("apostrophe", ..) => Ok(Marker::Apostrophe),
+            ("braces", ..) => Ok(Marker::Braces),
+            (_, Some(marker), None) if !marker.is_alphanumeric() => Ok(Marker::Char(marker)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Settings of a [`TextInterpolator`] that can be stored, see the [module docs](self) for the
+/// text format.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::config::{Config, Marker};
+/// use text_interpolator::TextInterpolator;
+///
+/// let config: Config = "marker = $\nstrict = true".parse().unwrap();
+/// assert_eq!(Marker::Char('$'), config.marker);
+///
+/// let mut text_interpolator = TextInterpolator::from_config(&config);
+/// let output = text_interpolator.interp("Hello $name", &|template| match template {
+///     "name" => Some("world"),
+///     _ => None,
+/// });
+///
+/// assert_eq!("Hello world", output.unwrap());
+/// assert_eq!(config, config.to_string().parse().unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub marker: Marker,
+    /// See [`TextInterpolator::max_depth`].
+    pub max_depth: usize,
+    /// See [`TextInterpolator::strict`].
+    pub strict: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            marker: Marker::default(),
+            max_depth: defaults::MAX_DEPTH,
+            strict: false,
+        }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "marker = {}", self.marker)?;
+        writeln!(f, "max_depth = {}", self.max_depth)?;
+        writeln!(f, "strict = {}", self.strict)
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || ConfigError {
+                line: index + 1,
+                text: line.to_string(),
+            };
+
+            let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+
+            match key.trim() {
+                "marker" => config.marker = value.parse().map_err(|_| invalid())?,
+                "max_depth" => config.max_depth = value.parse().map_err(|_| invalid())?,
+                "strict" => config.strict = value.parse().map_err(|_| invalid())?,
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// A line of text that isn't a known setting with a valid value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Line number, starting at 1.
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid setting on line {}: {}", self.line, self.text)
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+impl TextInterpolator {
+    /// Creates a TextInterpolator from stored settings, using the template syntax the config
+    /// names.
+    pub fn from_config(config: &Config) -> Self {
+        let builder = match &config.marker {
+            Marker::Apostrophe => TextInterpolatorBuilder::default(),
+            Marker::Char(marker) => TextInterpolatorBuilder::default().marker(*marker),
+            Marker::Braces => TextInterpolatorBuilder::default().braces(),
+            Marker::Delimiters { prefix, suffix } => {
+                TextInterpolatorBuilder::default().delimiters(prefix, suffix.as_deref())
+            }
+        };
+
+        builder
+            .max_depth(config.max_depth)
+            .strict(config.strict)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trips_through_text() {
+        for config in [
+            Config::default(),
+            Config {
+                marker: Marker::Braces,
+                max_depth: 3,
+                strict: true,
+            },
+            Config {
+                marker: Marker::Char('@'),
+                ..Default::default()
+            },
+            Config {
+                marker: Marker::Char('\''),
+                ..Default::default()
+            },
+            Config {
+                marker: Marker::Delimiters {
+                    prefix: "%%".to_string(),
+                    suffix: Some("%%".to_string()),
+                },
+                ..Default::default()
+            },
+            Config {
+                marker: Marker::Delimiters {
+                    prefix: "$$".to_string(),
+                    suffix: None,
+                },
+                ..Default::default()
+            },
+        ] {
+            assert_eq!(config, config.to_string().parse().unwrap());
+        }
+
+        let config: Config = "\n# comment\n  max_depth=8  \nmarker = apostrophe\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Config {
+                max_depth: 8,
+                ..Default::default()
+            },
+            config
+        );
+    }
+
+    #[test]
+    fn config_rejects_invalid_settings() {
+        for (text, line) in [
+            ("strict = yes", 1),
+            ("max_depth = 8\nmarker = ab", 2),
+            ("marker = a", 1),
+            ("marker = delimiters", 1),
+            ("marker = delimiters { } }", 1),
+            ("colour = red", 1),
+            ("\nstrict", 2),
+        ] {
+            assert_eq!(line, text.parse::<Config>().unwrap_err().line);
+        }
+    }
+
+    #[test]
+    fn from_config_uses_named_syntax() {
+        let map = |template: &str| match template {
+            "noun" => Some("place"),
+            _ => None,
+        };
+
+        let mut text_interpolator = TextInterpolator::from_config(&Config {
+            marker: Marker::Braces,
+            max_depth: 1,
+            strict: true,
+        });
+
+        assert_eq!(1, text_interpolator.max_depth);
+        assert_eq!(
+            "place 'noun",
+            text_interpolator.interp("{noun} 'noun", &map).unwrap()
+        );
+        assert!(text_interpolator.interp("{missing}", &map).is_err());
+
+        let mut text_interpolator = TextInterpolator::from_config(&Config::default());
+        assert_eq!("place", text_interpolator.interp("'noun", &map).unwrap());
+
+        let config: Config = "marker = delimiters %% %%".parse().unwrap();
+        let mut text_interpolator = TextInterpolator::from_config(&config);
+        assert_eq!(
+            "place 'noun",
+            text_interpolator.interp("%%noun%% 'noun", &map).unwrap()
+        );
+    }
+}
@@ -1,10 +1,20 @@
-use crate::TemplateSplit;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+
+use crate::{TemplateSplit, TextInterpolator};
+
+/// The default maximum number of nested substitutions.
+pub const MAX_DEPTH: usize = 64;
+
+/// The character that starts a template by default, as in `'name`.
+pub const DEFAULT_MARKER: char = '\'';
 
 /// Checks if a string is a template.
 ///
 /// The default implementation considers a string starting with an apostrophe to be a template.
 ///
-/// Returns None if the string is empty or does not contain an apostrophe as the first character.
+/// Returns false if the string is empty, does not contain an apostrophe as the first character,
+/// or is escaped with a doubled apostrophe (see [`unescape`]).
 ///
 /// # Examples
 ///
@@ -16,36 +26,216 @@ use crate::TemplateSplit;
 ///
 /// assert!(is_template(template));
 /// assert!(!is_template(not_template));
+/// assert!(!is_template("''tis"));
 /// ```
 pub fn is_template(text: &str) -> bool {
-    match text.chars().next() {
-        Some(c) => c == '\'',
-        None => false,
-    }
+    is_template_with_marker(text, DEFAULT_MARKER)
 }
 
+/// Extracts the template from a word, splitting it into the text before the apostrophe, the
+/// template name, and the text after the name.
+///
+/// A name is a run of name characters (see [`is_name_char`]), and any other character is a
+/// boundary that ends it (see [`is_boundary_char`]), so brackets and quotes around a template,
+/// as in `("'noun")` or `<'verb>.`, always land in the prefix or the suffix and never in the
+/// name. The template starts at the first apostrophe that begins the word or follows a
+/// character that isn't alphanumeric, see [`extract_template_with_marker`] for the full rules.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::extract_template;
+///
+/// let template_split = extract_template("[\"'noun\"],");
+///
+/// assert_eq!("[\"", template_split.prefix);
+/// assert_eq!("noun", template_split.template);
+/// assert_eq!("\"],", template_split.suffix);
+/// ```
 pub fn extract_template<'a>(embedded_template: &'a str) -> TemplateSplit<'a> {
+    extract_template_with_marker(embedded_template, DEFAULT_MARKER)
+}
+
+/// Unescapes a word that starts with a doubled apostrophe.
+///
+/// A word beginning with `''` is a literal word starting with a single apostrophe rather than a
+/// template. Unescaping removes the first apostrophe and leaves the rest of the word untouched.
+/// Returns None if the word is not escaped.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::unescape;
+///
+/// assert_eq!(Some("'tis"), unescape("''tis"));
+/// assert_eq!(Some("'"), unescape("''"));
+/// assert_eq!(None, unescape("'template"));
+/// assert_eq!(None, unescape("it''s"));
+/// ```
+pub fn unescape(text: &str) -> Option<&str> {
+    unescape_with_marker(text, DEFAULT_MARKER)
+}
+
+/// Checks if a string is a template using marker in place of the apostrophe.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::is_template_with_marker;
+///
+/// assert!(is_template_with_marker("$template", '$'));
+/// assert!(!is_template_with_marker("'template", '$'));
+/// assert!(!is_template_with_marker("$$5", '$'));
+/// ```
+pub fn is_template_with_marker(text: &str, marker: char) -> bool {
+    is_template_with_prefix(text, marker.encode_utf8(&mut [0; 4]))
+}
+
+/// Checks if a string is a template using a prefix of any length in place of the apostrophe,
+/// the same way as [`is_template_with_marker`].
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::is_template_with_prefix;
+///
+/// assert!(is_template_with_prefix("%%template", "%%"));
+/// assert!(!is_template_with_prefix("%template", "%%"));
+/// assert!(!is_template_with_prefix("%%%%5", "%%"));
+/// ```
+pub fn is_template_with_prefix(text: &str, prefix: &str) -> bool {
+    !prefix.is_empty() && text.starts_with(prefix) && unescape_with_prefix(text, prefix).is_none()
+}
+
+/// Extracts a template using marker in place of the apostrophe.
+///
+/// The template starts at the first marker that begins the string or follows a character that
+/// isn't alphanumeric, so contractions like `I'm` are never mistaken for templates, see
+/// [`split_at_marker`].
+///
+/// Digits are always part of a name, so indexed templates like `'arg0` and `'item2` are
+/// extracted whole, and a name may even be purely numeric. Hyphens at the end of a name are left
+/// in the suffix, so `'arg0-` is the template `arg0` followed by `-`.
+///
+/// A template name may be followed by `|` and a default value, as in `'noun|thing`, which is
+/// substituted when the map has no entry for the template. The default runs until the first
+/// character that is not a name character, the marker, or `|`, so it can itself be a template
+/// with a default of its own, as in `'noun|'other|thing`.
+///
+/// A name may also be followed by `#` and a count, as in `'noun#count` or `'noun#3`, to
+/// pluralize the substitution when the count is not 1. The count comes before any default.
+///
+/// Arguments for [`TextInterpolator::interp_args`] are written in parentheses directly after
+/// the name, as in `'repeat(3,star)`, and come before any count or default.
+///
+/// A bang between the marker and the name, as in `'!name`, defers the template to a later pass:
+/// the word is written with the bang removed instead of being substituted, see
+/// [`TemplateSplit::deferred`].
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::extract_template_with_marker;
+///
+/// let template_split = extract_template_with_marker("$noun|$other|thing.", '$');
+///
+/// assert_eq!("noun", template_split.template);
+/// assert_eq!(Some("$other|thing"), template_split.default);
+/// assert_eq!(".", template_split.suffix);
+///
+/// let template_split = extract_template_with_marker("$cat#lives,", '$');
+///
+/// assert_eq!("cat", template_split.template);
+/// assert_eq!(Some("lives"), template_split.count);
+/// assert_eq!(",", template_split.suffix);
+/// ```
+pub fn extract_template_with_marker(embedded_template: &str, marker: char) -> TemplateSplit<'_> {
+    extract_template_with_prefix(embedded_template, marker.encode_utf8(&mut [0; 4]))
+}
+
+/// Extracts a template using a prefix of any length in place of the apostrophe, following the
+/// same rules as [`extract_template_with_marker`]. A default may contain any character of the
+/// prefix, so it can be a template of its own.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::extract_template_with_prefix;
+///
+/// let template_split = extract_template_with_prefix("(%%noun|%%other).", "%%");
+///
+/// assert_eq!("(", template_split.prefix);
+/// assert_eq!("noun", template_split.template);
+/// assert_eq!(Some("%%other"), template_split.default);
+/// assert_eq!(").", template_split.suffix);
+/// ```
+pub fn extract_template_with_prefix<'a>(
+    embedded_template: &'a str,
+    marker: &str,
+) -> TemplateSplit<'a> {
     let prefix: &str;
     let template: &str;
     let suffix: &str;
+    let mut default = None;
+    let mut count = None;
+    let mut args = None;
+
+    let split = split_at_prefix(embedded_template, marker);
+
+    if let Some((before, after)) = split {
+        if let Some(name) = after.strip_prefix('!').filter(|name| name_length(name) > 0) {
+            return TemplateSplit {
+                prefix: &embedded_template[..before.len() + marker.len()],
+                suffix: name,
+                deferred: true,
+                ..Default::default()
+            };
+        }
+    }
+
+    match split {
+        Some((before, after)) => {
+            // The name runs until the first character that can't be part of a name, everything
+            // after it is kept as the suffix
+            let name_len = name_length(after);
+
+            prefix = before;
+            let mut rest;
+            (template, rest) = after.split_at(name_len);
 
-    match embedded_template.split_once('\'') {
-        Some(split) => match split.1.split_once(|ch: char| !ch.is_alphanumeric()) {
-            Some(inner_split) => {
-                prefix = split.0;
-                template = inner_split.0;
-                if inner_split.1.is_empty() {
-                    suffix = &split.1[split.1.len() - 1..];
-                } else {
-                    suffix = inner_split.1;
+            if let Some((value, after_args)) = rest
+                .strip_prefix('(')
+                .and_then(|after_open| after_open.split_once(')'))
+            {
+                if !template.is_empty() {
+                    args = Some(value);
+                    rest = after_args;
                 }
             }
-            None => {
-                prefix = split.0;
-                template = split.1;
-                suffix = "";
+
+            if let Some(after_count) = rest.strip_prefix('#') {
+                let count_len = name_length(after_count);
+
+                if !template.is_empty() && count_len > 0 {
+                    let value;
+                    (value, rest) = after_count.split_at(count_len);
+                    count = Some(value);
+                }
             }
-        },
+
+            match rest.strip_prefix('|') {
+                Some(rest) if !template.is_empty() => {
+                    let default_len = rest
+                        .find(|ch: char| !(is_name_char(ch) || marker.contains(ch) || ch == '|'))
+                        .unwrap_or(rest.len());
+                    let (value, rest) = rest.split_at(default_len);
+
+                    default = Some(value);
+                    suffix = rest;
+                }
+                _ => suffix = rest,
+            }
+        }
         None => {
             prefix = "";
             template = "";
@@ -57,19 +247,357 @@ pub fn extract_template<'a>(embedded_template: &'a str) -> TemplateSplit<'a> {
         prefix,
         template,
         suffix,
+        default,
+        count,
+        args,
+        deferred: false,
+    }
+}
+
+/// Splits a word around the marker that starts its template, returning the text before and
+/// after the marker, or None if the word has no template.
+///
+/// The template starts at the first marker that begins the word or follows a character that
+/// isn't alphanumeric, so contractions like `I'm` are skipped, and words escaped with a doubled
+/// marker never contain a template. This is the first step of [`extract_template_with_marker`],
+/// which then reads the name from the start of the text after the marker.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::{split_at_marker, DEFAULT_MARKER};
+///
+/// assert_eq!(Some(("(", "noun).")), split_at_marker("('noun).", DEFAULT_MARKER));
+/// assert_eq!(Some(("it's:", "adj")), split_at_marker("it's:'adj", DEFAULT_MARKER));
+/// assert_eq!(None, split_at_marker("''tis", DEFAULT_MARKER));
+/// assert_eq!(None, split_at_marker("I'm", DEFAULT_MARKER));
+/// ```
+pub fn split_at_marker(word: &str, marker: char) -> Option<(&str, &str)> {
+    split_at_prefix(word, marker.encode_utf8(&mut [0; 4]))
+}
+
+/// Splits a word around the prefix that starts its template, the same way as
+/// [`split_at_marker`] but with a prefix of any length. An empty prefix never starts a template.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::split_at_prefix;
+///
+/// assert_eq!(Some(("(", "noun).")), split_at_prefix("(${noun).", "${"));
+/// assert_eq!(Some(("(", "prix")), split_at_prefix("(€€prix", "€€"));
+/// assert_eq!(None, split_at_prefix("a%%b", "%%"));
+/// ```
+pub fn split_at_prefix<'a>(word: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    if prefix.is_empty() || unescape_with_prefix(word, prefix).is_some() {
+        return None;
     }
+
+    word.match_indices(prefix)
+        .find(|(index, _)| {
+            !word[..*index]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+        })
+        .map(|(index, _)| (&word[..index], &word[index + prefix.len()..]))
+}
+
+/// Checks if a character can be part of a template name.
+///
+/// Names are made of alphanumeric characters, underscores, and hyphens, so snake_case and
+/// kebab-case names are extracted whole.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::is_name_char;
+///
+/// assert!(is_name_char('é'));
+/// assert!(is_name_char('_'));
+/// assert!(!is_name_char('.'));
+/// ```
+pub fn is_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '-'
+}
+
+/// Checks if a character ends a template name, which is the case for every character that
+/// can't be part of one.
+///
+/// Boundaries include whitespace, every bracket such as `(`, `[`, `{`, and `<`, every quote
+/// such as `"`, `'`, `“`, and `«`, and all other punctuation and symbols other than the
+/// underscore and the hyphen. A few of them carry meaning right after a name: `(` opens
+/// arguments, `#` a count, and `|` a default, see [`extract_template_with_marker`].
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::is_boundary_char;
+///
+/// assert!(is_boundary_char(')'));
+/// assert!(is_boundary_char('”'));
+/// assert!(is_boundary_char('\''));
+/// assert!(!is_boundary_char('-'));
+/// assert!(!is_boundary_char('é'));
+/// ```
+pub fn is_boundary_char(ch: char) -> bool {
+    !is_name_char(ch)
+}
+
+/// Length of the name at the start of text. A name can't end in a hyphen, since one there is
+/// punctuation as in `'noun-'adj` rather than part of a kebab-case name.
+fn name_length(text: &str) -> usize {
+    let len = text.find(is_boundary_char).unwrap_or(text.len());
+
+    text[..len].trim_end_matches('-').len()
+}
+
+/// Unescapes a word that starts with a doubled marker.
+pub fn unescape_with_marker(text: &str, marker: char) -> Option<&str> {
+    unescape_with_prefix(text, marker.encode_utf8(&mut [0; 4]))
+}
+
+/// Unescapes a word that starts with a doubled prefix, removing the first one.
+pub fn unescape_with_prefix<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    text.strip_prefix(prefix)
+        .filter(|rest| !prefix.is_empty() && rest.starts_with(prefix))
+}
+
+/// Creates a TextInterpolator that uses marker as the template sigil in place of the apostrophe.
+///
+/// Template detection, extraction, and escaping all follow the default rules with the marker
+/// substituted, so `$$word` is a literal `$word` when the marker is `$`.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::with_marker;
+///
+/// let mut text_interpolator = with_marker('$');
+/// let map = |template: &str| match template {
+///     "greeting" => Some("$word, it's $$5".to_string()),
+///     "word" => Some("Hello".to_string()),
+///     _ => None,
+/// };
+///
+/// let output = text_interpolator.interp("$greeting 'word", &map).unwrap();
+///
+/// assert_eq!("Hello, it's $5 'word", output);
+/// ```
+pub fn with_marker(marker: char) -> TextInterpolator {
+    let mut text_interpolator = TextInterpolator::new(
+        move |text| is_template_with_marker(text, marker),
+        move |text| extract_template_with_marker(text, marker),
+    );
+    text_interpolator.unescape = Box::new(move |text| unescape_with_marker(text, marker));
+    text_interpolator.marker = Some(marker);
+    text_interpolator.conditional_delimiters = Some((marker.to_string(), String::new()));
+
+    text_interpolator
+}
+
+/// Checks if a string contains a brace-delimited template such as `{template}` anywhere in it.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::is_template_braces;
+///
+/// assert!(is_template_braces("{template}"));
+/// assert!(is_template_braces("before{template}after"));
+/// assert!(!is_template_braces("{unclosed"));
+/// assert!(!is_template_braces("}backwards{"));
+/// ```
+pub fn is_template_braces(text: &str) -> bool {
+    is_template_delimited(text, "{", "}")
+}
+
+/// Checks if a string contains a template enclosed between prefix and suffix anywhere in it,
+/// as in `%%template%%` or `${template}`.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::is_template_delimited;
+///
+/// assert!(is_template_delimited("%%template%%", "%%", "%%"));
+/// assert!(is_template_delimited("before${template}after", "${", "}"));
+/// assert!(!is_template_delimited("%%unclosed", "%%", "%%"));
+/// assert!(!is_template_delimited("{template}", "${", "}"));
+/// ```
+pub fn is_template_delimited(text: &str, prefix: &str, suffix: &str) -> bool {
+    match text.split_once(prefix) {
+        Some(split) => !prefix.is_empty() && split.1.contains(suffix),
+        None => false,
+    }
+}
+
+/// Extracts the first brace-delimited template from a string.
+///
+/// The prefix is everything before the opening brace, the template is everything between the
+/// braces, and the suffix is everything after the closing brace. Template names may contain any
+/// character other than a closing brace.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::extract_template_braces;
+///
+/// let template_split = extract_template_braces("before{first-name}after");
+///
+/// assert_eq!("before", template_split.prefix);
+/// assert_eq!("first-name", template_split.template);
+/// assert_eq!("after", template_split.suffix);
+/// ```
+pub fn extract_template_braces(embedded_template: &str) -> TemplateSplit<'_> {
+    extract_template_delimited(embedded_template, "{", "}")
+}
+
+/// Extracts the first template enclosed between prefix and suffix from a string, the same way
+/// as [`extract_template_braces`].
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::extract_template_delimited;
+///
+/// let template_split = extract_template_delimited("(%%first.name%%)", "%%", "%%");
+///
+/// assert_eq!("(", template_split.prefix);
+/// assert_eq!("first.name", template_split.template);
+/// assert_eq!(")", template_split.suffix);
+/// ```
+pub fn extract_template_delimited<'a>(
+    embedded_template: &'a str,
+    prefix: &str,
+    suffix: &str,
+) -> TemplateSplit<'a> {
+    let split = embedded_template
+        .split_once(prefix)
+        .filter(|_| !prefix.is_empty())
+        .and_then(|(prefix, rest)| Some((prefix, rest.split_once(suffix)?)));
+
+    match split {
+        Some((prefix, (template, suffix))) => TemplateSplit {
+            prefix,
+            template,
+            suffix,
+            ..Default::default()
+        },
+        None => TemplateSplit::default(),
+    }
+}
+
+/// Creates a TextInterpolator that recognizes brace-delimited templates such as `{template}`.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::with_braces;
+///
+/// let mut text_interpolator = with_braces();
+/// let map = |template: &str| match template {
+///     "user.name" => Some("world".to_string()),
+///     _ => None,
+/// };
+///
+/// let output = text_interpolator.interp("Hello, {user.name}!", &map).unwrap();
+///
+/// assert_eq!("Hello, world!", output);
+/// ```
+pub fn with_braces() -> TextInterpolator {
+    TextInterpolator {
+        delimited: true,
+        marker: Some('{'),
+        conditional_delimiters: Some(("{".to_string(), "}".to_string())),
+        ..TextInterpolator::new(is_template_braces, extract_template_braces)
+    }
+}
+
+/// Creates a TextInterpolator whose templates start with a prefix of any length and, when a
+/// suffix is given, end with it.
+///
+/// With only a prefix, as in `$name` or `%%name`, templates follow the same rules as
+/// [`with_marker`], including escaping with a doubled prefix. With a suffix, as in `${name}` or
+/// `%%name%%`, templates follow the rules of [`with_braces`] instead.
+/// Conditionals are written between the same delimiters, as in `%%if(premium: Thanks!)%%`.
+///
+/// # Panics
+///
+/// Panics if the prefix or the suffix is empty.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::defaults::with_delimiters;
+///
+/// let map = |template: &str| match template {
+///     "greeting" => Some("Hello %%user.name%%"),
+///     "user.name" => Some("Ada"),
+///     _ => None,
+/// };
+///
+/// let mut text_interpolator = with_delimiters("%%", Some("%%"));
+/// let output = text_interpolator.interp("%%greeting%%!", &map).unwrap();
+///
+/// assert_eq!("Hello Ada!", output);
+///
+/// let mut text_interpolator = with_delimiters("€€", None);
+/// let output = text_interpolator.interp("€€greeting, €€€€5", &map).unwrap();
+///
+/// assert_eq!("Hello %%user.name%%, €€5", output);
+/// ```
+pub fn with_delimiters(prefix: &str, suffix: Option<&str>) -> TextInterpolator {
+    assert!(!prefix.is_empty(), "template prefix is empty");
+    assert!(suffix != Some(""), "template suffix is empty");
+
+    let marker = prefix.chars().next();
+    let conditional_delimiters = (String::from(prefix), String::from(suffix.unwrap_or("")));
+    let prefix = String::from(prefix);
+
+    let mut text_interpolator = match suffix.map(String::from) {
+        Some(suffix) => {
+            let extract_prefix = prefix.clone();
+            let extract_suffix = suffix.clone();
+
+            TextInterpolator {
+                delimited: true,
+                ..TextInterpolator::new(
+                    move |text| is_template_delimited(text, &prefix, &suffix),
+                    move |text| extract_template_delimited(text, &extract_prefix, &extract_suffix),
+                )
+            }
+        }
+        None => {
+            let extract_prefix = prefix.clone();
+            let unescape_prefix = prefix.clone();
+
+            let mut text_interpolator = TextInterpolator::new(
+                move |text| is_template_with_prefix(text, &prefix),
+                move |text| extract_template_with_prefix(text, &extract_prefix),
+            );
+            text_interpolator.unescape =
+                Box::new(move |text| unescape_with_prefix(text, &unescape_prefix));
+            text_interpolator
+        }
+    };
+    text_interpolator.marker = marker;
+    text_interpolator.conditional_delimiters = Some(conditional_delimiters);
+
+    text_interpolator
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ValidationIssue;
 
     #[test]
     fn template_extration_with_prefix_and_suffix() {
         let extrated_template = extract_template("['adj.'..'.]");
         dbg!(&extrated_template);
         assert_eq!("[", extrated_template.prefix);
-        assert_eq!("'..'.]", extrated_template.suffix);
+        assert_eq!(".'..'.]", extrated_template.suffix);
         assert_eq!("adj", extrated_template.template);
     }
 
@@ -78,7 +606,7 @@ mod tests {
         let extrated_template = extract_template("'noun's");
         dbg!(&extrated_template);
         assert_eq!("", extrated_template.prefix);
-        assert_eq!("s", extrated_template.suffix);
+        assert_eq!("'s", extrated_template.suffix);
         assert_eq!("noun", extrated_template.template);
     }
 
@@ -109,6 +637,309 @@ mod tests {
         assert_eq!("noun", extrated_template.template);
     }
 
+    #[test]
+    fn template_extration_with_multiple_ending_punctuation() {
+        for (text, expected_suffix) in [("'noun..", ".."), ("'noun!?", "!?"), ("'noun...", "...")] {
+            let extrated_template = extract_template(text);
+            assert_eq!("", extrated_template.prefix);
+            assert_eq!(expected_suffix, extrated_template.suffix);
+            assert_eq!("noun", extrated_template.template);
+        }
+    }
+
+    #[test]
+    fn template_extration_with_snake_and_kebab_case() {
+        let extrated_template = extract_template("'first_name");
+        assert_eq!("", extrated_template.prefix);
+        assert_eq!("", extrated_template.suffix);
+        assert_eq!("first_name", extrated_template.template);
+
+        let extrated_template = extract_template("'user-id");
+        assert_eq!("", extrated_template.prefix);
+        assert_eq!("", extrated_template.suffix);
+        assert_eq!("user-id", extrated_template.template);
+    }
+
+    #[test]
+    fn template_extration_with_default() {
+        let extrated_template = extract_template("('noun|thing).");
+        assert_eq!("(", extrated_template.prefix);
+        assert_eq!(").", extrated_template.suffix);
+        assert_eq!("noun", extrated_template.template);
+        assert_eq!(Some("thing"), extrated_template.default);
+
+        let extrated_template = extract_template("'noun|'adj|thing");
+        assert_eq!("", extrated_template.suffix);
+        assert_eq!("noun", extrated_template.template);
+        assert_eq!(Some("'adj|thing"), extrated_template.default);
+
+        let extrated_template = extract_template("'noun");
+        assert_eq!(None, extrated_template.default);
+    }
+
+    #[test]
+    fn template_extration_ignores_contractions() {
+        for text in ["I'm", "don't", "rock'n'roll"] {
+            let extrated_template = extract_template(text);
+            assert_eq!("", extrated_template.template);
+        }
+
+        let extrated_template = extract_template("it's:'noun");
+        assert_eq!("it's:", extrated_template.prefix);
+        assert_eq!("noun", extrated_template.template);
+    }
+
+    #[test]
+    fn template_extration_with_digits() {
+        for (text, template, suffix) in [
+            ("'arg0", "arg0", ""),
+            ("'arg1.", "arg1", "."),
+            ("'item2,", "item2", ","),
+            ("'item-2", "item-2", ""),
+            ("'2nd", "2nd", ""),
+            ("'42", "42", ""),
+            ("'arg0.5", "arg0", ".5"),
+            ("'arg0-", "arg0", "-"),
+            ("'arg-0--x", "arg-0--x", ""),
+            ("'arg0--", "arg0", "--"),
+            ("'arg0-'arg1", "arg0", "-'arg1"),
+        ] {
+            let extrated_template = extract_template(text);
+            assert_eq!("", extrated_template.prefix);
+            assert_eq!(template, extrated_template.template);
+            assert_eq!(suffix, extrated_template.suffix);
+        }
+
+        let extrated_template = extract_template("'noun#n2-");
+        assert_eq!("noun", extrated_template.template);
+        assert_eq!(Some("n2"), extrated_template.count);
+        assert_eq!("-", extrated_template.suffix);
+    }
+
+    #[test]
+    fn interpolate_indexed_templates() {
+        let mut text_interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "arg0" => Some("zero"),
+            "arg1" => Some("one"),
+            "arg10" => Some("ten"),
+            _ => None,
+        };
+
+        let interpolated_text =
+            text_interpolator.interp("'arg0, 'arg1. 'arg10 'arg0-'arg1 'arg2", &map);
+
+        assert_eq!("zero, one. ten zero-one 'arg2", &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn template_extration_with_args() {
+        for (text, template, args, suffix) in [
+            ("'repeat(3,star).", "repeat", Some("3,star"), "."),
+            ("('now())", "now", Some(""), ")"),
+            ("'open(", "open", None, "("),
+            ("'name's(x)", "name", None, "'s(x)"),
+            ("'(x)", "", None, "(x)"),
+        ] {
+            let extrated_template = extract_template(text);
+            assert_eq!(template, extrated_template.template);
+            assert_eq!(args, extrated_template.args);
+            assert_eq!(suffix, extrated_template.suffix);
+        }
+
+        let extrated_template = extract_template("'pick(a,b)#n|none");
+        assert_eq!(Some("a,b"), extrated_template.args);
+        assert_eq!(Some("n"), extrated_template.count);
+        assert_eq!(Some("none"), extrated_template.default);
+    }
+
+    #[test]
+    fn template_extration_with_deferred_template() {
+        let extrated_template = extract_template("('!noun's)");
+        assert!(extrated_template.deferred);
+        assert_eq!("('", extrated_template.prefix);
+        assert_eq!("", extrated_template.template);
+        assert_eq!("noun's)", extrated_template.suffix);
+
+        for text in ["'!", "'!!", "it'!s", "''!noun"] {
+            assert!(!extract_template(text).deferred, "{text}");
+        }
+    }
+
+    #[test]
+    fn interpolate_deferred_templates() {
+        let mut text_interpolator = TextInterpolator {
+            strict: true,
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "noun" => Some("place".to_string()),
+            "later" => Some("'!noun".to_string()),
+            _ => None,
+        };
+
+        let interpolated_text =
+            text_interpolator.interp("'!noun ('!missing) 'noun'!noun 'later '!", &map);
+        assert_eq!(
+            "'noun ('missing) place'noun 'noun '!",
+            &interpolated_text.unwrap()
+        );
+        assert_eq!(
+            "place",
+            text_interpolator
+                .interp("'later", &map)
+                .and_then(|text| text_interpolator.interp(&text, &map))
+                .unwrap()
+        );
+        assert_eq!(
+            vec!["noun", "later"],
+            text_interpolator.find_templates("'!missing 'noun 'later")
+        );
+    }
+
+    /// Random words built from characters that are significant to template extraction, along
+    /// with multi-byte ones, so every combination of markers and punctuation is tried.
+    fn random_words(seed: u64) -> impl Iterator<Item = String> {
+        const CHARS: [char; 22] = [
+            '\'', '\'', '$', '!', '|', '#', '(', ')', '{', '}', '-', '_', '.', 'a', 'Z', '0', 'é',
+            '名', '\u{301}', '🎉', '%', '€',
+        ];
+        let mut rng = crate::random::Rng::new(seed);
+
+        (0..5000).map(move |_| {
+            let len = rng.next_usize() % 12;
+            (0..len)
+                .map(|_| CHARS[rng.next_usize() % CHARS.len()])
+                .collect()
+        })
+    }
+
+    /// Rebuilds the word a template was extracted from.
+    fn reassemble(template_split: &TemplateSplit, marker: &str) -> String {
+        if template_split.deferred {
+            return [template_split.prefix, "!", template_split.suffix].concat();
+        }
+
+        let mut word = [template_split.prefix, marker, template_split.template].concat();
+        if let Some(args) = template_split.args {
+            word += &["(", args, ")"].concat();
+        }
+        if let Some(count) = template_split.count {
+            word += &["#", count].concat();
+        }
+        if let Some(default) = template_split.default {
+            word += &["|", default].concat();
+        }
+
+        word + template_split.suffix
+    }
+
+    #[test]
+    fn template_extraction_reassembles_any_word() {
+        for word in random_words(1) {
+            for marker in [DEFAULT_MARKER, '$', '名'] {
+                let template_split = extract_template_with_marker(&word, marker);
+                if split_at_marker(&word, marker).is_none() {
+                    assert!(template_split.template.is_empty() && template_split.suffix.is_empty());
+                    continue;
+                }
+
+                assert!(!template_split.template.ends_with('-'), "{word:?}");
+                assert_eq!(
+                    word,
+                    reassemble(&template_split, marker.encode_utf8(&mut [0; 4])),
+                    "{template_split:?}"
+                );
+            }
+
+            for prefix in ["%%", "€€", "${"] {
+                let template_split = extract_template_with_prefix(&word, prefix);
+                if split_at_prefix(&word, prefix).is_some() {
+                    assert_eq!(
+                        word,
+                        reassemble(&template_split, prefix),
+                        "{template_split:?}"
+                    );
+                }
+
+                let template_split = extract_template_delimited(&word, prefix, "%€");
+                if is_template_delimited(&word, prefix, "%€") {
+                    assert_eq!(
+                        word,
+                        [
+                            template_split.prefix,
+                            prefix,
+                            template_split.template,
+                            "%€",
+                            template_split.suffix
+                        ]
+                        .concat()
+                    );
+                }
+            }
+
+            let template_split = extract_template_braces(&word);
+            if is_template_braces(&word) {
+                assert_eq!(
+                    word,
+                    [
+                        template_split.prefix,
+                        "{",
+                        template_split.template,
+                        "}",
+                        template_split.suffix
+                    ]
+                    .concat()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpolating_any_word_never_panics() {
+        let map = |template: &str| template.len().is_multiple_of(2).then_some("a'b 'Z|é");
+
+        for interpolator in [
+            TextInterpolator::default(),
+            with_marker('$'),
+            with_braces(),
+            with_delimiters("€€", None),
+            with_delimiters("%%", Some("%%")),
+        ] {
+            for word in random_words(2) {
+                let text = word.replace('_', " ");
+                let _ = interpolator.interp_ref(&text, &map);
+                let _ = interpolator.validate(&text, &map);
+            }
+        }
+    }
+
+    #[test]
+    fn template_extration_with_brackets_and_quotes() {
+        for (word, prefix, template, suffix) in [
+            ("('adj)", "(", "adj", ")"),
+            ("[\"'noun\"]", "[\"", "noun", "\"]"),
+            ("<'verb>", "<", "verb", ">"),
+            ("</'tag>", "</", "tag", ">"),
+            ("{'a}", "{", "a", "}"),
+            ("(('noun))", "((", "noun", "))"),
+            ("«'noun»", "«", "noun", "»"),
+            ("“'noun”", "“", "noun", "”"),
+            ("'noun'.", "", "noun", "'."),
+            ("('noun's)", "(", "noun", "'s)"),
+            ("\"'noun-\"", "\"", "noun", "-\""),
+            ("('_x_)", "(", "_x_", ")"),
+            ("'noun)(", "", "noun", ")("),
+        ] {
+            let extrated_template = extract_template(word);
+            assert_eq!(prefix, extrated_template.prefix, "{word:?}");
+            assert_eq!(template, extrated_template.template, "{word:?}");
+            assert_eq!(suffix, extrated_template.suffix, "{word:?}");
+            assert_eq!(None, extrated_template.args, "{word:?}");
+            assert!(!template.contains(is_boundary_char));
+        }
+    }
+
     #[test]
     fn template_extration_with_no_suffix_or_prefix() {
         let extrated_template = extract_template("'noun");
@@ -119,12 +950,187 @@ mod tests {
         assert_eq!("noun", extrated_template.template);
     }
 
+    #[test]
+    fn template_extration_with_escaped_marker() {
+        let extrated_template = extract_template("''tis");
+        assert!(!is_template("''tis"));
+        assert_eq!("", extrated_template.prefix);
+        assert_eq!("", extrated_template.suffix);
+        assert_eq!("", extrated_template.template);
+    }
+
+    #[test]
+    fn template_extration_with_custom_marker() {
+        let extrated_template = extract_template_with_marker("(@noun)", '@');
+        assert_eq!("(", extrated_template.prefix);
+        assert_eq!(")", extrated_template.suffix);
+        assert_eq!("noun", extrated_template.template);
+    }
+
+    #[test]
+    fn interpolate_nested_templates_with_custom_marker() {
+        let mut text_interpolator = with_marker('@');
+        let map = |template: &str| match template {
+            "sentence" => Some("A @adj @noun.".to_string()),
+            "adj" => Some("funny".to_string()),
+            "noun" => Some("'place".to_string()),
+            _ => None,
+        };
+
+        let interpolated_text = text_interpolator.interp("@sentence @@sentence", &map);
+
+        assert_eq!("A funny 'place. @sentence", &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn template_extration_with_braces() {
+        let extrated_template = extract_template_braces("before{noun}after");
+        assert_eq!("before", extrated_template.prefix);
+        assert_eq!("after", extrated_template.suffix);
+        assert_eq!("noun", extrated_template.template);
+
+        let extrated_template = extract_template_braces("{my_template's!}");
+        assert_eq!("", extrated_template.prefix);
+        assert_eq!("", extrated_template.suffix);
+        assert_eq!("my_template's!", extrated_template.template);
+    }
+
+    #[test]
+    fn template_extration_with_unclosed_braces() {
+        let extrated_template = extract_template_braces("before{noun");
+        assert_eq!("", extrated_template.prefix);
+        assert_eq!("", extrated_template.suffix);
+        assert_eq!("", extrated_template.template);
+    }
+
+    #[test]
+    fn interpolate_nested_templates_with_braces() {
+        let mut text_interpolator = with_braces();
+        let map = |template: &str| match template {
+            "sentence" => Some("A {adj} ({noun}).".to_string()),
+            "adj" => Some("funny".to_string()),
+            "noun" => Some("'place".to_string()),
+            _ => None,
+        };
+
+        let interpolated_text = text_interpolator.interp("[{sentence}]", &map);
+
+        assert_eq!("[A funny ('place).]", &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn interpolate_adjacent_templates_with_braces() {
+        let mut text_interpolator = with_braces();
+        let map = |template: &str| match template {
+            "a" => Some("A".to_string()),
+            "b" => Some("B".to_string()),
+            "c" => Some("C".to_string()),
+            "ab" => Some("{a}{b}".to_string()),
+            _ => None,
+        };
+
+        for (text, interpolated_text) in [
+            ("{a}{b}{c}", "ABC"),
+            ("pre{a}mid{b}post", "preAmidBpost"),
+            ("{a}{missing}{c}", "A{missing}C"),
+            ("{missing}{a}", "{missing}A"),
+            ("x{ab}y{c}", "xAByC"),
+        ] {
+            assert_eq!(
+                interpolated_text,
+                text_interpolator.interp(text, &map).unwrap()
+            );
+        }
+
+        let issues = text_interpolator.validate("{a}{missing}{c}{x}", &map);
+        assert_eq!(
+            Err(vec![
+                ValidationIssue::UndefinedTemplate("missing".to_string()),
+                ValidationIssue::UndefinedTemplate("x".to_string()),
+            ]),
+            issues
+        );
+
+        text_interpolator.strict = true;
+        assert!(text_interpolator.interp("{a}{missing}", &map).is_err());
+    }
+
     #[test]
     fn template_extration_with_nested_template() {
         let extrated_template = extract_template("'noun'noun");
         dbg!(&extrated_template);
         assert_eq!("", extrated_template.prefix);
-        assert_eq!("noun", extrated_template.suffix);
+        assert_eq!("'noun", extrated_template.suffix);
         assert_eq!("noun", extrated_template.template);
     }
+
+    #[test]
+    fn template_extration_with_multi_byte_characters() {
+        for (text, prefix, template, suffix) in [
+            ("'café.", "", "café", "."),
+            ("«'naïve»", "«", "naïve", "»"),
+            ("🎉'名前🎉!", "🎉", "名前", "🎉!"),
+            ("¿'noun…?", "¿", "noun", "…?"),
+            ("'é", "", "é", ""),
+            ("’'ß’", "’", "ß", "’"),
+        ] {
+            let extrated_template = extract_template(text);
+            assert_eq!(prefix, extrated_template.prefix);
+            assert_eq!(template, extrated_template.template);
+            assert_eq!(suffix, extrated_template.suffix);
+        }
+
+        let extrated_template = extract_template_with_marker("€prix€€", '€');
+        assert_eq!("prix", extrated_template.template);
+        assert_eq!("€€", extrated_template.suffix);
+
+        let extrated_template = extract_template_braces("✨{ключ}✨");
+        assert_eq!("✨", extrated_template.prefix);
+        assert_eq!("ключ", extrated_template.template);
+        assert_eq!("✨", extrated_template.suffix);
+    }
+
+    #[test]
+    fn interpolate_nested_templates_with_delimiters() {
+        let map = |template: &str| match template {
+            "sentence" => Some("A ${adj}-${noun}."),
+            "adj" => Some("€€noun!"),
+            "noun" => Some("%%noun%%"),
+            _ => None,
+        };
+
+        let mut text_interpolator = with_delimiters("${", Some("}"));
+        assert_eq!(
+            "(A €€noun!-%%noun%%.) {noun}",
+            text_interpolator
+                .interp("(${sentence}) {noun}", &map)
+                .unwrap()
+        );
+
+        let mut text_interpolator = with_delimiters("€€", None);
+        assert_eq!(
+            "«%%noun%%!» €€adj €adj €",
+            text_interpolator
+                .interp("«€€adj» €€€€adj €adj €", &map)
+                .unwrap()
+        );
+        assert!(matches!(
+            text_interpolator.interp("€€adj|x", &|_| Some("€€adj")),
+            Err(crate::InterpError::NestedTemplateLoop(_))
+        ));
+    }
+
+    #[test]
+    fn interpolate_multi_byte_text() {
+        let mut text_interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "café" => Some("☕ 'Crème"),
+            "crème" => Some("brûlée"),
+            _ => None,
+        };
+
+        let interpolated_text = text_interpolator.interp("«'café»… ''émigré 'ñ🎉", &map);
+
+        assert_eq!("«☕ Brûlée»… 'émigré 'ñ🎉", &interpolated_text.unwrap());
+    }
 }
@@ -0,0 +1,251 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{ignore_args, CallState, InterpError, Sink, Substitution, Suspended, TextInterpolator};
+
+/// The substitutions made while interpolating text, as returned by
+/// [`TextInterpolator::explain`].
+///
+/// The tree is stored flat, with every substitution in expansion order and its children
+/// referred to by index, so trees of any depth can be cloned, compared, and dropped without
+/// recursion. Displaying it renders the expansion as an indented tree, one substitution per
+/// line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpansionTree {
+    pub output: String,
+    /// Every substitution in the order it was made, so each comes before its children.
+    pub expansions: Vec<Expansion>,
+    /// Indices in expansions of the substitutions of the templates in the input, in the order
+    /// they appear.
+    pub roots: Vec<usize>,
+}
+
+/// A single substitution in an [`ExpansionTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    /// Template as written, including any case directive.
+    pub template: String,
+    /// The value chosen for the template, before the templates in it were expanded.
+    pub value: String,
+    /// Indices in [`ExpansionTree::expansions`] of the substitutions of the templates in the
+    /// value.
+    pub children: Vec<usize>,
+}
+
+impl fmt::Display for ExpansionTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Expansions still to be written with their indent, the next one last
+        let mut stack: Vec<(usize, usize)> =
+            self.roots.iter().rev().map(|&index| (0, index)).collect();
+
+        while let Some((indent, index)) = stack.pop() {
+            let expansion = &self.expansions[index];
+            writeln!(
+                f,
+                "{:indent$}{} => {:?}",
+                "",
+                expansion.template,
+                expansion.value,
+                indent = indent * 2
+            )?;
+            stack.extend(
+                expansion
+                    .children
+                    .iter()
+                    .rev()
+                    .map(|&child| (indent + 1, child)),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl TextInterpolator {
+    /// Interpolates text the same way as [`TextInterpolator::interp`], returning the tree of
+    /// substitutions that produced the output, for debugging grammars whose output is
+    /// unexpected.
+    ///
+    /// Memoization is turned off meanwhile, so every substitution is expanded and shows up in
+    /// the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let tree = text_interpolator.explain("'greeting, 'name!", &map).unwrap();
+    ///
+    /// assert_eq!("Hello world, world!", tree.output);
+    /// assert_eq!(
+    ///     "greeting => \"Hello 'name\"\n  name => \"world\"\nname => \"world\"\n",
+    ///     tree.to_string()
+    /// );
+    /// ```
+    pub fn explain<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<ExpansionTree, InterpError> {
+        let mut state = CallState {
+            expansions: Some(Vec::new()),
+            ..Default::default()
+        };
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        Suspended::new(self).text_interpolator.interp_call(
+            text,
+            &ignore_args(map),
+            &mut state,
+            Sink::String(&mut output),
+        )?;
+
+        let (expansions, roots) = build_tree(state.expansions.unwrap_or_default());
+
+        Ok(ExpansionTree {
+            output,
+            expansions,
+            roots,
+        })
+    }
+}
+
+/// Links substitutions recorded in expansion order to the substitution they were found in,
+/// which is the closest earlier one of a lower depth, returning them along with the indices of
+/// those found in the input.
+fn build_tree(records: Vec<(String, String, usize)>) -> (Vec<Expansion>, Vec<usize>) {
+    let mut expansions: Vec<Expansion> = Vec::with_capacity(records.len());
+    let mut roots = Vec::new();
+    // Indices of the substitutions whose children are still being added, one per depth
+    let mut path: Vec<usize> = Vec::new();
+
+    for (template, value, depth) in records {
+        path.truncate(depth.saturating_sub(1));
+
+        let index = expansions.len();
+        match path.last() {
+            Some(&parent) => expansions[parent].children.push(index),
+            None => roots.push(index),
+        }
+        expansions.push(Expansion {
+            template,
+            value,
+            children: Vec::new(),
+        });
+        path.push(index);
+    }
+
+    (expansions, roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explain_nests_substitutions_under_their_template() {
+        let mut text_interpolator = TextInterpolator {
+            memoize: true,
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "sentence" => Some("A 'adj 'noun'punct"),
+            "adj" => Some("'very funny"),
+            "very" => Some("very"),
+            "noun" => Some("place"),
+            "punct" => Some("!"),
+            _ => None,
+        };
+
+        let tree = text_interpolator
+            .explain("'sentence 'Noun 'missing|'adj", &map)
+            .unwrap();
+
+        assert_eq!(
+            text_interpolator
+                .interp("'sentence 'Noun 'missing|'adj", &map)
+                .unwrap(),
+            tree.output
+        );
+        assert_eq!(
+            concat!(
+                "sentence => \"A 'adj 'noun'punct\"\n",
+                "  adj => \"'very funny\"\n",
+                "    very => \"very\"\n",
+                "  noun => \"place\"\n",
+                "  punct => \"!\"\n",
+                "Noun => \"place\"\n",
+                "missing => \"'adj\"\n",
+                "  adj => \"'very funny\"\n",
+                "    very => \"very\"\n",
+            ),
+            tree.to_string()
+        );
+        assert_eq!(vec![0, 5, 6], tree.roots);
+        assert_eq!(vec![1, 3, 4], tree.expansions[0].children);
+        assert_eq!("very", tree.expansions[2].template);
+        assert!(text_interpolator.memoize);
+
+        assert!(text_interpolator
+            .explain("'missing", &map)
+            .unwrap()
+            .expansions
+            .is_empty());
+        assert!(matches!(
+            text_interpolator.explain("'loop", &|_| Some("'loop")),
+            Err(InterpError::NestedTemplateLoop(err)) if err.chain == ["loop", "loop"]
+        ));
+    }
+
+    #[test]
+    fn deep_trees_are_handled_without_recursion() {
+        fn map_chain(template: &str) -> Option<String> {
+            let n: usize = template.strip_prefix("chain")?.parse().ok()?;
+            match n {
+                200_000 => Some("end".to_string()),
+                _ => Some(format!("'chain{}", n + 1)),
+            }
+        }
+
+        let mut text_interpolator = TextInterpolator {
+            max_depth: usize::MAX,
+            ..Default::default()
+        };
+
+        let tree = text_interpolator.explain("'chain0", &map_chain).unwrap();
+
+        assert_eq!("end", tree.output);
+        let mut depth = 0;
+        let mut expansion = tree.roots.first();
+        while let Some(&index) = expansion {
+            depth += 1;
+            expansion = tree.expansions[index].children.first();
+        }
+        assert_eq!(200_001, depth);
+        assert_eq!(tree, tree.clone());
+        drop(tree);
+
+        // Rendering is quadratic in the depth because of the indent, so a shallower tree is
+        // rendered on a thread with a small stack instead
+        let tree = text_interpolator
+            .explain("'chain198000", &map_chain)
+            .unwrap();
+        let rendered = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || tree.to_string())
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(2001, rendered.lines().count());
+        assert!(rendered.ends_with(&format!("{:4000}chain200000 => \"end\"\n", "")));
+    }
+}
@@ -6,183 +6,5226 @@
 //! template, extract it, and then map it to it's substitute.
 //!
 //! It also supports nested templates requiring recursion to reach a valid substitute.
+//!
+//! The crate is `no_std` compatible and only needs `alloc` when the default `std` feature is
+//! disabled. Without it the `HashMap` based APIs and [`TextInterpolator::interp_stream`] are
+//! unavailable, and the random number generator starts from a fixed seed instead of entropy.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+mod builder;
+pub mod case;
+mod conditional;
+pub mod config;
 pub mod defaults;
+mod explain;
+#[cfg(feature = "parallel")]
+mod parallel;
+pub mod plural;
+pub mod random;
+#[cfg(feature = "std")]
+mod simple;
+#[cfg(feature = "std")]
+mod stream;
+mod substitution;
+mod substitutor;
+mod tokens;
+mod validate;
+
+pub use builder::TextInterpolatorBuilder;
+pub use explain::{Expansion, ExpansionTree};
+#[cfg(feature = "std")]
+pub use simple::SimpleInterpolator;
+pub use substitution::{Literal, Substitution, Template};
+pub use substitutor::{Chain, Substitutor};
+pub use tokens::Token;
+pub use validate::ValidationIssue;
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use core::ops::Range;
+
+use conditional::OffsetMap;
+use random::{Choice, ChoiceLog};
+#[cfg(feature = "std")]
+use std::borrow::Borrow;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestedTemplateLoopError {
+    /// Names of the templates forming the cycle, in expansion order, starting and ending with
+    /// the template that was entered twice.
+    pub chain: Vec<String>,
+    /// Depth of the substitution that would have entered the template again, counting
+    /// templates in the input text as depth 1.
+    pub depth: usize,
+}
+
+impl fmt::Display for NestedTemplateLoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "detected infinitely looping nested templates at depth {}: {}",
+            self.depth,
+            self.chain.join(" -> ")
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InterpError {
+    NestedTemplateLoop(NestedTemplateLoopError),
+    MaxDepthExceeded {
+        depth: usize,
+    },
+    /// A template has no substitution in strict mode. The span is the byte range of the word
+    /// of the input text the template was found in, or for a template nested in a substitution,
+    /// of the word whose expansion it was found in.
+    UndefinedTemplate {
+        template: String,
+        span: Range<usize>,
+    },
+    /// A template is not one of the registered known templates while unknown templates are
+    /// rejected, see [`TextInterpolator::register_templates`]. The span is the same as for
+    /// [`InterpError::UndefinedTemplate`].
+    UnknownTemplate {
+        template: String,
+        span: Range<usize>,
+    },
+    /// The output grew past the interpolator's max output length.
+    OutputTooLarge {
+        limit: usize,
+    },
+    /// Bytes given to [`TextInterpolator::interp_bytes`] are not valid UTF-8.
+    InvalidUtf8(core::str::Utf8Error),
+    /// The writer given to [`TextInterpolator::interp_fmt`] failed.
+    Write(fmt::Error),
+}
+
+impl fmt::Display for InterpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpError::NestedTemplateLoop(err) => err.fmt(f),
+            InterpError::MaxDepthExceeded { depth } => {
+                write!(
+                    f,
+                    "nested templates exceeded the maximum depth at depth {depth}"
+                )
+            }
+            InterpError::UndefinedTemplate { template, span } => {
+                write!(
+                    f,
+                    "template '{template}' has no substitution at bytes {}..{}",
+                    span.start, span.end
+                )
+            }
+            InterpError::UnknownTemplate { template, span } => {
+                write!(
+                    f,
+                    "template '{template}' at bytes {}..{} is not a known template",
+                    span.start, span.end
+                )
+            }
+            InterpError::OutputTooLarge { limit } => {
+                write!(f, "output exceeded the maximum length of {limit} bytes")
+            }
+            InterpError::InvalidUtf8(err) => write!(f, "input is not valid UTF-8: {err}"),
+            InterpError::Write(_) => write!(f, "failed to write the output"),
+        }
+    }
+}
+
+impl core::error::Error for NestedTemplateLoopError {}
+
+impl core::error::Error for InterpError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            InterpError::NestedTemplateLoop(err) => Some(err),
+            InterpError::InvalidUtf8(err) => Some(err),
+            InterpError::Write(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl InterpError {
+    /// Moves the span of an undefined template by offset bytes, for text interpolated in parts.
+    fn offset_span(mut self, offset: usize) -> Self {
+        if let InterpError::UndefinedTemplate { span, .. }
+        | InterpError::UnknownTemplate { span, .. } = &mut self
+        {
+            *span = span.start + offset..span.end + offset;
+        }
+
+        self
+    }
+}
+
+impl From<NestedTemplateLoopError> for InterpError {
+    fn from(err: NestedTemplateLoopError) -> Self {
+        InterpError::NestedTemplateLoop(err)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TemplateSplit<'a> {
+    pub prefix: &'a str,
+    pub template: &'a str,
+    pub suffix: &'a str,
+    /// Text substituted in place of the template when the map has no entry for it. It is
+    /// interpolated like any other substitution, so it may contain templates of its own.
+    pub default: Option<&'a str>,
+    /// Count the substitution is pluralized for when it is not 1, either a number or the name of
+    /// a template whose substitution is a number.
+    pub count: Option<&'a str>,
+    /// Comma-separated arguments passed to the map along with the template name, see
+    /// [`TextInterpolator::interp_args`].
+    pub args: Option<&'a str>,
+    /// Whether the template is deferred with a bang after the marker, as in `'!name`, so it is
+    /// written without the bang instead of being substituted. The template is then left empty,
+    /// and the word is written as the prefix, which ends with the marker, followed by the suffix,
+    /// which starts with the name.
+    pub deferred: bool,
+}
+
+/// What to do with a template that has no substitution.
+///
+/// Every action other than `Keep` only affects the template itself, the prefix and suffix
+/// around it are written as usual.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::{TextInterpolator, UnresolvedAction};
+///
+/// let mut text_interpolator = TextInterpolator::default();
+/// let map = |_: &str| None::<String>;
+///
+/// text_interpolator.on_unresolved = UnresolvedAction::StripMarker;
+/// assert_eq!("Hello (name)", text_interpolator.interp("Hello ('name)", &map).unwrap());
+///
+/// text_interpolator.on_unresolved = UnresolvedAction::Remove;
+/// assert_eq!("Hello ()", text_interpolator.interp("Hello ('name)", &map).unwrap());
+///
+/// text_interpolator.on_unresolved = UnresolvedAction::Replace("[missing]".to_string());
+/// assert_eq!("Hello ([missing])", text_interpolator.interp("Hello ('name)", &map).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum UnresolvedAction {
+    /// Leaves the template in the output as written.
+    #[default]
+    Keep,
+    /// Writes the template name without its marker, between the prefix and suffix around it,
+    /// so `('noun).` becomes `(noun).`, which turns text with an incomplete dictionary into a
+    /// readable draft. Anything written after the name, such as arguments, a count, or a
+    /// default that didn't resolve either, is left out.
+    StripMarker,
+    /// Writes nothing in place of the template.
+    Remove,
+    /// Writes the given text in place of the template.
+    Replace(String),
+}
+
+/// A piece of interpolated output, recording whether it came from the input text or from a
+/// substitution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Literal(String),
+    Substituted { template: String, value: String },
+}
+
+/// Figures about a single interpolation, returned by [`TextInterpolator::interp_with_stats`]
+/// to help tune [`TextInterpolator::max_depth`] and [`TextInterpolator::capacity_hint`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of substitutions performed, including nested ones, where a memoized value that
+    /// is reused counts once without the substitutions nested in it.
+    pub substitutions: usize,
+    /// Deepest nesting reached, 1 for templates of the input text and 0 if nothing was
+    /// substituted.
+    pub max_depth_reached: usize,
+    /// Number of distinct templates substituted, counting names as written.
+    pub unique_templates: usize,
+    /// Length of the output in bytes.
+    pub output_len: usize,
+}
+
+pub type IsTemplateFn = Box<dyn Fn(&str) -> bool + Send + Sync>;
+pub type ExtractTemplateFn = Box<dyn for<'a> Fn(&'a str) -> TemplateSplit<'a> + Send + Sync>;
+pub type UnescapeFn = Box<dyn for<'a> Fn(&'a str) -> Option<&'a str> + Send + Sync>;
+pub type RngFn = Box<dyn FnMut() -> usize + Send + Sync>;
+pub type SeparatorFn = Box<dyn Fn(char) -> bool + Send + Sync>;
+pub type RecurseFn = Box<dyn Fn(&str) -> bool + Send + Sync>;
+pub type SubstituteFn = Box<dyn FnMut(&str, &str, usize) + Send + Sync>;
+
+/// Interpolates templates in text using configurable template syntax.
+///
+/// A TextInterpolator is `Send` and `Sync`, so it can be moved to another thread or shared
+/// behind an `Arc`. Loop detection and other bookkeeping live on the stack of each call, so
+/// between calls the interpolator holds nothing but its configuration. Most methods take
+/// `&mut self` so they can use the rng and the on_substitute hook, but
+/// [`TextInterpolator::interp_ref`] only borrows the interpolator, so a shared one can
+/// interpolate from many threads at once without locking.
+pub struct TextInterpolator {
+    pub is_template: IsTemplateFn,
+    pub extract_template: ExtractTemplateFn,
+    /// Maps an escaped literal word to its unescaped form, or returns None if the word is not
+    /// escaped. Applied to every word of the output that was not substituted.
+    pub unescape: UnescapeFn,
+    /// Checks if a character separates words of the input, defaulting to `char::is_whitespace`.
+    /// Runs of separators are copied to the output exactly as they appear.
+    pub is_separator: SeparatorFn,
+    /// Whether templates are delimited on both sides, as with braces, so a template that doesn't
+    /// resolve still ends where its suffix starts. Templates after it in the same word, as in
+    /// `{first}{last}`, are then substituted as well instead of being written as they are.
+    /// Enabled by [`defaults::with_braces`], and by [`defaults::with_delimiters`] given a suffix.
+    pub delimited: bool,
+    /// A character every template and escaped word contains, such as the marker, so text
+    /// without it is copied as is without being split into words. Set by the constructors in
+    /// [`defaults`] and [`TextInterpolator::default`], but None for [`TextInterpolator::new`],
+    /// so it has to be updated when is_template or extract_template are replaced.
+    pub marker: Option<char>,
+    /// Prefix and suffix written around conditionals, as in `'if(premium: Thanks!)` with the
+    /// default prefix `'` and no suffix. The constructors in [`defaults`] use the delimiters of
+    /// their templates, so braces write `{if(premium: Thanks!)}`. The prefix must contain the
+    /// marker. None, the default for [`TextInterpolator::new`], disables conditionals.
+    pub conditional_delimiters: Option<(String, String)>,
+    /// Decides whether templates inside the substitution of the named template are expanded,
+    /// defaulting to always. When it returns false the substitution is written exactly as the
+    /// map returned it, even if it contains template markers, so a template can be made to
+    /// expand only once while others nest as deep as max_depth allows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// text_interpolator.recurse = Box::new(|template| template != "code");
+    ///
+    /// let map = |template: &str| match template {
+    ///     "code" => Some("let x = 'name;"),
+    ///     "quote" => Some("'name said"),
+    ///     "name" => Some("Ada"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp("'quote: 'code", &map).unwrap();
+    ///
+    /// assert_eq!("Ada said: let x = 'name;", output);
+    /// ```
+    pub recurse: RecurseFn,
+    /// Whether substitutions are expanded at all, defaulting to true. When false every
+    /// substitution is written verbatim as the map returned it, as if recurse always returned
+    /// false, and no loop detection is done, which suits simple variable replacement.
+    pub recursive: bool,
+    /// Whether a template found again inside its own expansion fails with
+    /// [`InterpError::NestedTemplateLoop`], defaulting to true.
+    ///
+    /// Disable it for recursive grammars that re-enter a template on purpose and end through a
+    /// candidate that doesn't, such as a list whose candidates are `'item 'list` and `'item`.
+    /// Expansions that really never end are then stopped by max_depth instead, failing with
+    /// [`InterpError::MaxDepthExceeded`].
+    pub detect_loops: bool,
+    /// Treats substitutions from the map as literal data, written verbatim as if wrapped in a
+    /// [`Literal`], unless the map wraps them in a [`Template`]. Defaults to false, which
+    /// expands templates in every substitution.
+    ///
+    /// Enable it when the map returns untrusted data, such as user names or database fields,
+    /// whose markers, as in `it's`, must never be taken for templates, while still expanding
+    /// the grammar the map marks as templates. Defaults written in the text are always
+    /// expanded.
+    pub literal_by_default: bool,
+    /// Maximum number of nested substitutions allowed before interp fails with
+    /// [`InterpError::MaxDepthExceeded`]. A depth of 0 disables substitution entirely.
+    pub max_depth: usize,
+    /// Maximum length in bytes of the output of a single text, past which interp fails with
+    /// [`InterpError::OutputTooLarge`]. Guards against templates that expand exponentially
+    /// without ever looping, such as untrusted templates that each use several others.
+    /// Substitutions are checked as they are written, so the limit is exceeded by at most one
+    /// substitution before giving up. None, the default, allows any length.
+    pub max_output_len: Option<usize>,
+    /// Makes interp fail with [`InterpError::UndefinedTemplate`] on the first template that has
+    /// no substitution, instead of leaving it in the output unchanged.
+    pub strict: bool,
+    /// Names of the templates a grammar defines, filled by
+    /// [`TextInterpolator::register_templates`].
+    pub known_templates: BTreeSet<String>,
+    /// Makes interp fail with [`InterpError::UnknownTemplate`] on the first template that is
+    /// not in known_templates, before the map is asked for it. Whether a template is valid is
+    /// then decided by the registered names rather than by the map having a substitution at
+    /// the moment. A name written with a case directive, such as `'Noun`, is known when its
+    /// lowercase form is.
+    pub reject_unknown: bool,
+    /// Names that resolve as if the template they point to was written instead, added with
+    /// [`TextInterpolator::add_alias`].
+    pub aliases: BTreeMap<String, String>,
+    /// What to write in place of a template that has no substitution.
+    pub on_unresolved: UnresolvedAction,
+    /// Caches the fully resolved substitution of each template while interpolating a text, so
+    /// repeat occurrences reuse it instead of calling the map and expanding it again.
+    ///
+    /// Only enable this for deterministic maps, since every occurrence of a template gets the
+    /// same value. Random substitutions through [`TextInterpolator::interp_random`] and
+    /// [`TextInterpolator::interp_weighted`] never use the cache so each occurrence stays
+    /// independent. Reused values don't count towards the nested substitutions reported by
+    /// [`TextInterpolator::interp_counted`].
+    pub memoize: bool,
+    /// Makes every occurrence of a template within one call resolve to the substitute picked
+    /// for its first occurrence, so `'name said to 'name` names the same person twice even when
+    /// [`TextInterpolator::interp_random`] chooses among candidates. This is the opposite of
+    /// the independent draws random interpolation does by default.
+    ///
+    /// The pick is shared by all depths of nesting, and templates nested in a picked substitute
+    /// are sticky in turn, so repeats expand identically throughout. Counts still pluralize
+    /// each occurrence on its own. Defaults and templates with arguments are never sticky, and
+    /// nothing is kept from one call to the next.
+    pub sticky: bool,
+    /// Normalizes the whitespace substitutions bring into the output, defaulting to false,
+    /// which writes substitutions exactly as the map returned them.
+    ///
+    /// When enabled, separators at the start and end of each substituted value are removed,
+    /// and every run of separators inside it is shortened to its first separator, so that
+    /// `"  bright   red "` substituted into `a 'color car` gives `a bright red car`. This
+    /// applies to everything a template expands into, including text from nested substitutions
+    /// and defaults, but never to the separators of the input text itself, which
+    /// are always written as they appear. A substitution that is only separators counts as
+    /// empty and takes a neighboring run of input separators with it, as described in
+    /// [`TextInterpolator::interp`].
+    pub trim_substitutions: bool,
+    /// Expected ratio of output length to input length, used to reserve the output up front.
+    /// Raising it avoids repeated reallocation when short texts expand into long ones, such as
+    /// a single template that generates whole paragraphs. Defaults to 1.
+    pub capacity_hint: usize,
+    /// Lets the case of a template name as written carry over to its substitution when the map
+    /// has no entry for that exact name. `'Noun` then uses the entry for `noun` with its first
+    /// letter capitalized, and `'NOUN` uses it uppercased. The case is applied once nested
+    /// templates in the substitution are resolved, and also to defaults. With
+    /// [`TextInterpolator::case_insensitive_lookup`], the case applies even when the exact
+    /// name has an entry, since only the lowercase name is looked up.
+    pub case_directives: bool,
+    /// Looks every template up by its lowercase name, for dictionaries whose keys are all
+    /// lowercase, so `'Noun` and `'NOUN` both pass `noun` to the map. Defaults to false.
+    ///
+    /// The case the name is written in is still remembered: with
+    /// [`TextInterpolator::case_directives`] on, `'Noun` writes the entry for `noun`
+    /// capitalized and `'NOUN` writes it uppercased, even when the map would also have an
+    /// entry for the name as written. Turning case directives off writes the entry as it is,
+    /// however the name is written. Aliases, registered templates, and allowed templates are
+    /// matched against the lowercase name as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator {
+    ///     case_insensitive_lookup: true,
+    ///     ..Default::default()
+    /// };
+    /// let map = |template: &str| match template {
+    ///     "noun" => Some("place"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp("'noun, 'Noun, 'NOUN, 'nOUN", &map);
+    /// assert_eq!("place, Place, PLACE, place", output.unwrap());
+    ///
+    /// text_interpolator.case_directives = false;
+    /// let output = text_interpolator.interp("'noun, 'Noun, 'NOUN, 'nOUN", &map);
+    /// assert_eq!("place, place, place, place", output.unwrap());
+    /// ```
+    pub case_insensitive_lookup: bool,
+    /// Pluralizes substitutions of templates with a count, see [`plural`].
+    pub pluralizer: plural::Pluralizer,
+    /// Source of random numbers used to choose among substitution candidates. Seeded from
+    /// entropy by default when the `std` feature is enabled, see [`TextInterpolator::seed`] for
+    /// reproducible output.
+    pub rng: RngFn,
+    /// Called on every substitution with the template name as written, the substitute chosen
+    /// for it before nested templates are resolved, and the depth of the substitution, which is
+    /// 1 for templates of the input text. Useful to log the expansion tree or to find out which
+    /// templates of a library are actually used.
+    ///
+    /// Methods that only borrow the interpolator, such as [`TextInterpolator::interp_ref`],
+    /// can't call it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let fired = Arc::new(Mutex::new(Vec::new()));
+    /// let log = Arc::clone(&fired);
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// text_interpolator.on_substitute = Some(Box::new(move |template, value, depth| {
+    ///     log.lock().unwrap().push(format!("{depth} {template}={value}"));
+    /// }));
+    ///
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    /// text_interpolator.interp("'greeting!", &map).unwrap();
+    ///
+    /// assert_eq!(vec!["1 greeting=Hello 'name", "2 name=world"], *fired.lock().unwrap());
+    /// ```
+    pub on_substitute: Option<SubstituteFn>,
+}
+
+/// Bookkeeping for a single interpolation, created on the stack of each call and passed down
+/// through the expansion.
+#[derive(Default)]
+struct CallState {
+    template_set: BTreeSet<String>,
+    /// Templates currently being expanded, in the order they were entered.
+    template_chain: Vec<String>,
+    unresolved: Option<Vec<String>>,
+    substitutions: usize,
+    /// Deepest substitution made so far.
+    max_depth_reached: usize,
+    /// Templates substituted so far, for [`TextInterpolator::interp_with_stats`].
+    substituted_templates: Option<BTreeSet<String>>,
+    /// Number of substitutions after which templates are left as they are.
+    max_substitutions: Option<usize>,
+    /// Names of the only templates substituted, the rest are left as they are.
+    allowed: Option<BTreeSet<String>>,
+    memo: BTreeMap<String, String>,
+    /// Substitutes picked for each template name when sticky is enabled.
+    sticky: BTreeMap<String, Substitution<'static>>,
+    /// Templates of the input, the range of the output holding their substitution, and the
+    /// byte offset of the input word they were found in.
+    spans: Option<Vec<(String, Range<usize>, usize)>>,
+    /// The interpolator's on_substitute hook, moved here for the duration of a call.
+    on_substitute: Option<SubstituteFn>,
+    /// Every substitution in the order it was made, with its depth, for
+    /// [`TextInterpolator::explain`].
+    expansions: Option<Vec<(String, String, usize)>>,
+    /// Length of the output before the call started writing to it.
+    output_start: usize,
+    /// Length of the output already handed to the writer of [`Sink::Writer`].
+    flushed: usize,
+    /// Byte range in the input of the word being interpolated.
+    word_span: Range<usize>,
+}
+
+/// Where a call writes its output.
+enum Sink<'o> {
+    /// The output is pushed onto a String, where substitutions are rewritten in place.
+    String(&'o mut String),
+    /// The output is built in a buffer and handed to the writer as soon as it can no longer
+    /// change.
+    Writer(&'o mut dyn fmt::Write),
+}
+
+impl Default for TextInterpolator {
+    /// Creates a TextInterpolator with a default is_template function and default extract_template
+    /// function
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    /// ```
+    fn default() -> Self {
+        TextInterpolator {
+            is_template: Box::new(defaults::is_template),
+            extract_template: Box::new(defaults::extract_template),
+            unescape: Box::new(defaults::unescape),
+            is_separator: Box::new(char::is_whitespace),
+            delimited: false,
+            marker: Some(defaults::DEFAULT_MARKER),
+            conditional_delimiters: Some((defaults::DEFAULT_MARKER.to_string(), String::new())),
+            recurse: Box::new(|_| true),
+            recursive: true,
+            detect_loops: true,
+            literal_by_default: false,
+            max_depth: defaults::MAX_DEPTH,
+            max_output_len: None,
+            strict: false,
+            known_templates: BTreeSet::new(),
+            reject_unknown: false,
+            aliases: BTreeMap::new(),
+            on_unresolved: UnresolvedAction::Keep,
+            memoize: false,
+            sticky: false,
+            trim_substitutions: false,
+            capacity_hint: 1,
+            case_directives: true,
+            case_insensitive_lookup: false,
+            pluralizer: plural::Pluralizer::default(),
+            rng: entropy_rng(),
+            on_substitute: None,
+        }
+    }
+}
+
+impl TextInterpolator {
+    /// Creates a TextInterpolator from custom is_template and extract_template functions.
+    ///
+    /// Both functions may be closures that capture state, so template detection can depend on
+    /// configuration decided at runtime.
+    ///
+    /// No escape convention is assumed for custom templates, so words are never unescaped unless
+    /// the unescape field is set afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{TemplateSplit, TextInterpolator};
+    ///
+    /// let marker = '$';
+    /// let mut text_interpolator = TextInterpolator::new(
+    ///     move |text| text.starts_with(marker),
+    ///     move |text| match text.split_once(marker) {
+    ///         Some((prefix, template)) => TemplateSplit {
+    ///             prefix,
+    ///             template,
+    ///             ..Default::default()
+    ///         },
+    ///         None => TemplateSplit::default(),
+    ///     },
+    /// );
+    ///
+    /// let output = text_interpolator
+    ///     .interp("Hello $name", &|template| match template {
+    ///         "name" => Some("world".to_string()),
+    ///         _ => None,
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello world", output);
+    /// ```
+    pub fn new(
+        is_template: impl Fn(&str) -> bool + Send + Sync + 'static,
+        extract_template: impl Fn(&str) -> TemplateSplit<'_> + Send + Sync + 'static,
+    ) -> Self {
+        TextInterpolator {
+            is_template: Box::new(is_template),
+            extract_template: Box::new(extract_template),
+            unescape: Box::new(|_| None),
+            is_separator: Box::new(char::is_whitespace),
+            delimited: false,
+            marker: None,
+            conditional_delimiters: None,
+            recurse: Box::new(|_| true),
+            recursive: true,
+            detect_loops: true,
+            literal_by_default: false,
+            max_depth: defaults::MAX_DEPTH,
+            max_output_len: None,
+            strict: false,
+            known_templates: BTreeSet::new(),
+            reject_unknown: false,
+            aliases: BTreeMap::new(),
+            on_unresolved: UnresolvedAction::Keep,
+            memoize: false,
+            sticky: false,
+            trim_substitutions: false,
+            capacity_hint: 1,
+            case_directives: true,
+            case_insensitive_lookup: false,
+            pluralizer: plural::Pluralizer::default(),
+            rng: entropy_rng(),
+            on_substitute: None,
+        }
+    }
+
+    /// Interpolates text, replacing each template with the substitution map returns for it.
+    ///
+    /// The map may return anything that converts into a [`Substitution`], such as a `String` or
+    /// a borrowed `&str`, so static dictionaries don't need to allocate on every lookup. Wrapping
+    /// the value in a [`Literal`] writes it verbatim, without expanding any templates in it, and
+    /// wrapping it in a [`Template`] expands it even when
+    /// [`TextInterpolator::literal_by_default`] is set.
+    ///
+    /// A template directly following another in the same word, as in `'greeting'punctuation`,
+    /// is substituted as well when the map resolves it. Otherwise the rest of the word is kept
+    /// as is, so possessives like `'noun's` are left alone even in strict mode.
+    ///
+    /// A word that comes out empty, such as a blank `'middlename`, takes one neighboring run of
+    /// separators with it so no doubled or dangling space is left behind: the run after it, or
+    /// the run before it at the end of the text. Runs holding a line break are never taken, so
+    /// an empty word ending a line takes the space before it rather than the line break after
+    /// it, and interpolating line by line gives the same output. Words with a prefix or suffix
+    /// around the template, like `('middlename)`, aren't empty and are written as usual.
+    ///
+    /// A conditional, written `'if(premium: Thanks for subscribing!)` at the start of a word,
+    /// is replaced by its body when the map resolves the condition to a value that isn't empty,
+    /// `false`, or `0` once trimmed, and removed along with one neighboring run of separators
+    /// otherwise. Conditions that don't resolve are false, even in strict mode. A condition is
+    /// otherwise treated like any other template: it is false when a call's allowed templates
+    /// or substitution limit would leave it unresolved, and fails with
+    /// [`InterpError::UnknownTemplate`] when it isn't registered while
+    /// [`TextInterpolator::reject_unknown`] is set.
+    ///
+    /// Expansion is lazy: templates are only looked up and expanded once the text holding them
+    /// is written to the output. A default is never expanded when the map resolves its
+    /// template, nor are candidates that weren't chosen, so branches that are never taken can't
+    /// fail with a loop or an undefined template.
+    ///
+    /// A panic in map propagates to the caller, but doesn't poison the interpolator: every
+    /// call keeps its bookkeeping to itself, and settings a call changes for its duration are
+    /// put back while unwinding, so the interpolator can be used again as if the call never
+    /// happened.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp("'greeting!", &map).unwrap();
+    ///
+    /// assert_eq!("Hello world!", output);
+    /// ```
+    pub fn interp<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<String, InterpError> {
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_into(text, map, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Interpolates text given as bytes, for callers that pass UTF-8 around as `&[u8]`, the
+    /// same way as [`TextInterpolator::interp`].
+    ///
+    /// The bytes are checked to be UTF-8 up front, so templates and words are always split
+    /// on character boundaries. Invalid UTF-8 fails with [`InterpError::InvalidUtf8`] rather
+    /// than being replaced or causing a panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{InterpError, TextInterpolator};
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("wörld"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp_bytes(b"Hello 'name", &map).unwrap();
+    ///
+    /// assert_eq!("Hello wörld".as_bytes(), output);
+    /// assert!(matches!(
+    ///     text_interpolator.interp_bytes(b"Hello \xff'name", &map),
+    ///     Err(InterpError::InvalidUtf8(err)) if err.valid_up_to() == 6
+    /// ));
+    /// ```
+    pub fn interp_bytes<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        bytes: &[u8],
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<Vec<u8>, InterpError> {
+        let text = core::str::from_utf8(bytes).map_err(InterpError::InvalidUtf8)?;
+
+        self.interp(text, map).map(String::into_bytes)
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`] for maps that lend slices
+    /// of a long-lived store rather than returning owned strings.
+    ///
+    /// Substitutions are only ever borrowed from the store: they are copied straight into the
+    /// output, and nested templates are expanded by reading the borrowed slice. Nothing is
+    /// allocated for a substitution unless it has to be changed first, as when a case
+    /// directive, a count, or [`TextInterpolator::trim_substitutions`] rewrites it, or kept
+    /// for [`TextInterpolator::memoize`] or [`TextInterpolator::sticky`]. This is what
+    /// [`TextInterpolator::interp`] does for any map returning `&str`, and this method only
+    /// spells out the map type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let store = HashMap::from([
+    ///     ("greeting".to_string(), "Hello 'name".to_string()),
+    ///     ("name".to_string(), "world".to_string()),
+    /// ]);
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let output = text_interpolator
+    ///     .interp_borrowed("'Greeting!", &|template| store.get(template).map(String::as_str))
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello world!", output);
+    /// ```
+    pub fn interp_borrowed<'m>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<&'m str>,
+    ) -> Result<String, InterpError> {
+        self.interp(text, map)
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`] through a shared borrow,
+    /// keeping loop detection and other bookkeeping local to the call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let text_interpolator = Arc::new(TextInterpolator::default());
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let shared = Arc::clone(&text_interpolator);
+    /// let output = thread::spawn(move || shared.interp_ref("Hello 'name", &map).unwrap());
+    ///
+    /// assert_eq!("Hello world", output.join().unwrap());
+    /// ```
+    pub fn interp_ref<'m, S: Into<Substitution<'m>>>(
+        &self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<String, InterpError> {
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_nested(
+            text,
+            &ignore_args(map),
+            &mut CallState::default(),
+            &mut output,
+            None,
+        )?;
+
+        Ok(output)
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`] for maps of static
+    /// strings, collecting borrowed pieces of the text and substitutions and joining them once
+    /// instead of growing the output as it goes.
+    ///
+    /// The fast path only applies when no substitution contains a template of its own and every
+    /// template resolves to a plain value without a count, arguments, or case directive. Otherwise
+    /// the text is interpolated again the general way, calling map a second time, so the output
+    /// is always the same as with [`TextInterpolator::interp`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello"),
+    ///     "name" => Some("world"),
+    ///     "nested" => Some("'greeting"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp_static("'greeting, 'name!", &map);
+    /// assert_eq!("Hello, world!", output.unwrap());
+    ///
+    /// let output = text_interpolator.interp_static("'nested 'missing", &map);
+    /// assert_eq!("Hello 'missing", output.unwrap());
+    /// ```
+    pub fn interp_static(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<&'static str>,
+    ) -> Result<String, InterpError> {
+        if let Some(pieces) = self.static_pieces(text, map) {
+            let output = pieces.concat();
+            if self
+                .max_output_len
+                .is_none_or(|limit| output.len() <= limit)
+            {
+                return Ok(output);
+            }
+        }
+
+        self.interp(text, map)
+    }
+
+    /// Splits the output of interpolating text into borrowed pieces, or returns None if
+    /// anything needs more than copying a piece of the text or a substitution.
+    fn static_pieces<'t>(
+        &'t self,
+        text: &'t str,
+        map: &impl Fn(&str) -> Option<&'static str>,
+    ) -> Option<Vec<&'t str>> {
+        if self.sticky
+            || self.reject_unknown
+            || !self.aliases.is_empty()
+            || self.on_substitute.is_some()
+            || self.trim_substitutions
+            || self.contains_conditional(text)
+        {
+            return None;
+        }
+
+        let map = ignore_args(map);
+        let literal = |is_word: bool, item: &'t str| match is_word {
+            true => (self.unescape)(item).unwrap_or(item),
+            false => item,
+        };
+
+        let mut pieces = Vec::new();
+
+        for (is_word, item) in self.split_words(text) {
+            let template_split = (self.extract_template)(item);
+            if template_split.deferred {
+                pieces.extend([template_split.prefix, template_split.suffix]);
+                continue;
+            }
+            if !is_word || template_split.template.is_empty() {
+                pieces.push(literal(is_word, item));
+                continue;
+            }
+
+            if template_split.count.is_some()
+                || template_split.args.is_some()
+                || template_split.default.is_some()
+            {
+                return None;
+            }
+
+            // Empty substitutions take separators with them
+            let Ok(Resolved {
+                substitute: Some(Cow::Borrowed(substitute)),
+                case: Case::AsIs,
+                literal: verbatim,
+                ..
+            }) = self.resolve(&template_split, &map, 1)
+            else {
+                return None;
+            };
+            if substitute.is_empty() {
+                return None;
+            }
+
+            // The rest of the word is only copied as is if it has no template that resolves
+            let suffix = template_split.suffix;
+            let adjacent = (self.extract_template)(suffix);
+            if adjacent.deferred
+                || !adjacent.template.is_empty()
+                    && (self.delimited
+                        || self
+                            .resolve(&adjacent, &map, 1)
+                            .is_ok_and(|resolved| resolved.substitute.is_some()))
+            {
+                return None;
+            }
+
+            pieces.push(template_split.prefix);
+            if self.recursive && !verbatim && (self.recurse)(template_split.template) {
+                if self.contains_template(substitute) {
+                    return None;
+                }
+                pieces.extend(
+                    self.split_words(substitute)
+                        .map(|(is_word, piece)| literal(is_word, piece)),
+                );
+            } else {
+                pieces.push(substitute);
+            }
+            pieces.push(suffix);
+        }
+
+        Some(pieces)
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], also passing the map the
+    /// arguments written in parentheses after a template name, so templates can work like
+    /// functions.
+    ///
+    /// `'repeat(3,star)` calls the map with `repeat` and `["3", "star"]`, with whitespace around
+    /// each argument trimmed, while a bare `'name` gets an empty slice. Since text is split into
+    /// words before templates are extracted, arguments can't contain separators such as spaces.
+    /// The other interpolation methods ignore arguments and pass only the name to their map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str, args: &[&str]| match (template, args) {
+    ///     ("repeat", [count, word]) => Some(format!("'{word} ").repeat(count.parse().ok()?)),
+    ///     ("star", []) => Some("*".to_string()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp_args("'repeat(3,star)", &map).unwrap();
+    ///
+    /// assert_eq!("* * * ", output);
+    /// ```
+    pub fn interp_args<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+    ) -> Result<String, InterpError> {
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_call(
+            text,
+            map,
+            &mut CallState::default(),
+            Sink::String(&mut output),
+        )?;
+
+        Ok(output)
+    }
+
+    /// Adds names to the known templates of a fixed grammar, so referencing any other template
+    /// is an error once [`TextInterpolator::reject_unknown`] is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{InterpError, TextInterpolator};
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// text_interpolator.register_templates(["name".to_string(), "title".to_string()]);
+    /// text_interpolator.reject_unknown = true;
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("Ada"),
+    ///     "secret" => Some("hunter2"),
+    ///     _ => None,
+    /// };
+    ///
+    /// assert_eq!("Ada 'title", text_interpolator.interp("'name 'title", &map).unwrap());
+    /// assert!(matches!(
+    ///     text_interpolator.interp("'Name 'secret", &map),
+    ///     Err(InterpError::UnknownTemplate { template, .. }) if template == "secret"
+    /// ));
+    /// ```
+    pub fn register_templates(&mut self, names: impl IntoIterator<Item = String>) {
+        self.known_templates.extend(names);
+    }
+
+    /// Makes the template from resolve as if it were the template to, so several names can
+    /// share one entry of the map, as with `'colour` and `'color`.
+    ///
+    /// Aliases are followed, through any number of other aliases, before the map is consulted,
+    /// and the substitution is expanded under the name they lead to. Aliases leading back to
+    /// themselves fail with [`InterpError::NestedTemplateLoop`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{InterpError, TextInterpolator};
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// text_interpolator.add_alias("colour", "color");
+    /// let map = |template: &str| match template {
+    ///     "color" => Some("red"),
+    ///     _ => None,
+    /// };
+    ///
+    /// assert_eq!("red or Red", text_interpolator.interp("'color or 'Colour", &map).unwrap());
+    ///
+    /// text_interpolator.add_alias("color", "colour");
+    /// assert!(matches!(
+    ///     text_interpolator.interp("'colour", &map),
+    ///     Err(InterpError::NestedTemplateLoop(err)) if err.chain == ["colour", "color", "colour"]
+    /// ));
+    /// ```
+    pub fn add_alias(&mut self, from: &str, to: &str) {
+        self.aliases.insert(from.to_string(), to.to_string());
+    }
+
+    /// Follows aliases from a template name to the name it is looked up under.
+    fn unalias<'a>(
+        &'a self,
+        template: &'a str,
+        depth: usize,
+    ) -> Result<&'a str, NestedTemplateLoopError> {
+        let mut chain: Vec<&str> = Vec::new();
+        let mut name = template;
+
+        while let Some(to) = self.aliases.get(name) {
+            chain.push(name);
+            if let Some(start) = chain.iter().position(|entered| entered == to) {
+                let mut chain: Vec<String> = chain[start..].iter().map(|s| s.to_string()).collect();
+                chain.push(to.clone());
+                return Err(NestedTemplateLoopError { chain, depth });
+            }
+            name = to;
+        }
+
+        Ok(name)
+    }
+
+    /// Checks whether a template is registered, directly or through its lowercase form when it
+    /// is looked up by it.
+    fn is_known_template(&self, template: &str) -> bool {
+        self.known_templates.contains(template)
+            || (self.folds_case(template)
+                && self.known_templates.contains(&template.to_lowercase()))
+    }
+
+    /// Whether the substitution limit and the allowed templates of a call let a template be
+    /// substituted.
+    fn is_allowed(&self, template: &str, state: &CallState) -> bool {
+        let within_limit = state
+            .max_substitutions
+            .is_none_or(|max_substitutions| state.substitutions < max_substitutions);
+        let allowed = state.allowed.as_ref().is_none_or(|allowed| {
+            allowed.contains(template)
+                || self.folds_case(template) && allowed.contains(&template.to_lowercase())
+        });
+
+        within_limit && allowed
+    }
+
+    /// Whether a template fails since it isn't registered while unknown templates are rejected.
+    fn is_rejected(&self, template: &str) -> bool {
+        self.reject_unknown
+            && self.max_depth > 0
+            && !template.is_empty()
+            && !self.is_known_template(template)
+    }
+
+    /// Whether a template may be looked up by its lowercase name, because lookups ignore case
+    /// or because it is written with a case directive.
+    fn folds_case(&self, template: &str) -> bool {
+        self.case_insensitive_lookup || (self.case_directives && Case::of(template) != Case::AsIs)
+    }
+
+    /// Seeds the random number generator so random substitutions are reproducible.
+    ///
+    /// This replaces any custom rng with the built-in [`random::Rng`].
+    pub fn seed(&mut self, seed: u64) {
+        let mut rng = random::Rng::new(seed);
+        self.rng = Box::new(move || rng.next_usize());
+    }
+
+    /// Interpolates text where map offers several candidate substitutions for a template, one of
+    /// which is chosen at random using [`TextInterpolator::rng`].
+    ///
+    /// A template whose candidate list is empty is left unresolved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// text_interpolator.seed(42);
+    ///
+    /// let map = |template: &str| match template {
+    ///     "verb" => Some(vec!["run".to_string(), "fall".to_string(), "fly".to_string()]),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp_random("I 'verb", &map).unwrap();
+    ///
+    /// assert!(["I run", "I fall", "I fly"].contains(&output.as_str()));
+    /// ```
+    pub fn interp_random(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<Vec<String>>,
+    ) -> Result<String, InterpError> {
+        self.with_rng(|text_interpolator, rng| {
+            text_interpolator.interp(text, &|template| {
+                let mut candidates = map(template)?;
+                let index = random::choose_index(&mut *rng.borrow_mut(), candidates.len())?;
+                Some(candidates.swap_remove(index))
+            })
+        })
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp_random`], also returning
+    /// the log of every choice made, so the output can be reproduced or partly regenerated
+    /// with [`TextInterpolator::interp_replay`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "verb" => Some(vec!["run".to_string(), "fall".to_string(), "fly".to_string()]),
+    ///     _ => None,
+    /// };
+    ///
+    /// let (output, log) = text_interpolator.interp_random_logged("I 'verb", &map).unwrap();
+    ///
+    /// assert_eq!(1, log.choices.len());
+    /// assert_eq!("verb", log.choices[0].template);
+    /// assert_eq!(output, text_interpolator.interp_replay("I 'verb", &map, &log).unwrap().0);
+    /// ```
+    pub fn interp_random_logged(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<Vec<String>>,
+    ) -> Result<(String, ChoiceLog), InterpError> {
+        self.interp_replay(text, map, &ChoiceLog::default())
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp_random`], reusing the
+    /// choices of a log instead of drawing them, and returns the log of the choices made.
+    ///
+    /// Each occurrence of a template takes the candidate logged for the same occurrence, so
+    /// replaying a log with the input and map it was recorded with reproduces the output,
+    /// whatever the rng. Occurrences the log has no choice for, or a choice past the end of
+    /// the candidates, are drawn at random, so removing choices from a log regenerates those
+    /// parts of the text and keeps the rest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// text_interpolator.seed(1);
+    /// let map = |template: &str| match template {
+    ///     "line" => Some((0..100).map(|line| format!("line{line}")).collect()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let (first, mut log) = text_interpolator.interp_random_logged("'line 'line", &map).unwrap();
+    ///
+    /// // Keep the first line, regenerate the second
+    /// log.choices.retain(|choice| choice.occurrence == 0);
+    /// let (second, _) = text_interpolator.interp_replay("'line 'line", &map, &log).unwrap();
+    ///
+    /// assert_eq!(first.split(' ').next(), second.split(' ').next());
+    /// ```
+    pub fn interp_replay(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<Vec<String>>,
+        log: &ChoiceLog,
+    ) -> Result<(String, ChoiceLog), InterpError> {
+        let choices = RefCell::new(ChoiceLog::default());
+        let occurrences: RefCell<BTreeMap<String, usize>> = RefCell::new(BTreeMap::new());
+
+        let output = self.with_rng(|text_interpolator, rng| {
+            text_interpolator.interp(text, &|template| {
+                let mut candidates = map(template)?;
+                let mut occurrences = occurrences.borrow_mut();
+                let occurrence = occurrences.entry(template.to_string()).or_default();
+
+                let index = match log.index_of(template, *occurrence) {
+                    Some(index) if index < candidates.len() => index,
+                    _ => random::choose_index(&mut *rng.borrow_mut(), candidates.len())?,
+                };
+                choices.borrow_mut().choices.push(Choice {
+                    template: template.to_string(),
+                    occurrence: *occurrence,
+                    index,
+                });
+                *occurrence += 1;
+
+                Some(candidates.swap_remove(index))
+            })
+        })?;
+
+        Ok((output, choices.into_inner()))
+    }
+
+    /// Interpolates text where map offers weighted candidate substitutions for a template, one of
+    /// which is chosen at random with probability proportional to its weight.
+    ///
+    /// Candidates with a weight of 0 are never chosen, unless every candidate has a weight of 0,
+    /// in which case each is equally likely. A template whose candidate list is empty is left
+    /// unresolved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// text_interpolator.seed(42);
+    ///
+    /// let map = |template: &str| match template {
+    ///     "article" => Some(vec![(70, "the".to_string()), (30, "a".to_string())]),
+    ///     "never" => Some(vec![(0, "never".to_string()), (1, "always".to_string())]),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp_weighted("'article 'never", &map).unwrap();
+    ///
+    /// assert!(output == "the always" || output == "a always");
+    /// ```
+    pub fn interp_weighted(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<Vec<(u32, String)>>,
+    ) -> Result<String, InterpError> {
+        self.with_rng(|text_interpolator, rng| {
+            text_interpolator.interp(text, &|template| {
+                let mut candidates = map(template)?;
+                let weights: Vec<u32> = candidates.iter().map(|(weight, _)| *weight).collect();
+                let index = random::choose_weighted(&mut *rng.borrow_mut(), &weights)?;
+                Some(candidates.swap_remove(index).1)
+            })
+        })
+    }
+
+    /// Interpolates text where map offers several candidate substitutions for a template, and
+    /// successive occurrences of the template cycle through them in order.
+    ///
+    /// The first `'color` gets the first candidate, the next one the second, and so on, wrapping
+    /// around after the last. Each call starts counting occurrences afresh, nested ones included,
+    /// so the same text always interpolates the same way. The map may keep state of its own,
+    /// since it is called once per lookup. Memoization is turned off meanwhile, and a template
+    /// whose candidate list is empty is left unresolved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let mut map = |template: &str| match template {
+    ///     "color" => Some(vec!["red".to_string(), "green".to_string()]),
+    ///     "pair" => Some(vec!["'color and 'color".to_string()]),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp_cycle("'color, 'pair", &mut map).unwrap();
+    ///
+    /// assert_eq!("red, green and red", output);
+    /// ```
+    pub fn interp_cycle(
+        &mut self,
+        text: &str,
+        map: &mut impl FnMut(&str) -> Option<Vec<String>>,
+    ) -> Result<String, InterpError> {
+        let suspended = Suspended::new(self);
+        let mut occurrences: BTreeMap<String, usize> = BTreeMap::new();
+
+        suspended
+            .text_interpolator
+            .interp_mut(text, &mut |template| {
+                let mut candidates = map(template)?;
+                if candidates.is_empty() {
+                    return None;
+                }
+
+                let occurrence = occurrences.entry(template.to_string()).or_default();
+                let index = *occurrence % candidates.len();
+                *occurrence += 1;
+                Some(candidates.swap_remove(index))
+            })
+    }
+
+    /// Moves the rng out for the duration of f so it can be used from inside a map, which only
+    /// gets a shared borrow.
+    ///
+    /// Memoization is turned off meanwhile so every occurrence of a template is drawn separately.
+    fn with_rng<T>(&mut self, f: impl FnOnce(&mut Self, &RefCell<RngFn>) -> T) -> T {
+        let suspended = Suspended::new(self);
+        f(suspended.text_interpolator, &suspended.rng)
+    }
+
+    /// Interpolates text with numbered templates such as `'0` and `'1` substituted by the
+    /// argument at that index, for quick formatting without writing a map.
+    ///
+    /// Arguments are written verbatim, the same as a [`Literal`], so markers in them are never
+    /// expanded. Indices past the end of args, and templates that aren't numbers, have no
+    /// substitution and are handled by [`TextInterpolator::on_unresolved`] and
+    /// [`TextInterpolator::strict`] as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    ///
+    /// let output = text_interpolator
+    ///     .interp_positional("'1, '0 said: ''0 is '2.", &["Ada", "Hello", "'zero"])
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello, Ada said: '0 is 'zero.", output);
+    /// ```
+    pub fn interp_positional(&mut self, text: &str, args: &[&str]) -> Result<String, InterpError> {
+        self.interp(text, &|template: &str| {
+            let index = template.parse::<usize>().ok()?;
+            args.get(index).map(|arg| Literal(*arg))
+        })
+    }
+
+    /// Interpolates text using a dictionary of substitutions instead of a map function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = HashMap::from([("greeting", "Hello 'name"), ("name", "world")]);
+    ///
+    /// let output = text_interpolator.interp_map("'greeting!", &map).unwrap();
+    ///
+    /// assert_eq!("Hello world!", output);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn interp_map<K, V, S>(
+        &mut self,
+        text: &str,
+        map: &HashMap<K, V, S>,
+    ) -> Result<String, InterpError>
+    where
+        K: Borrow<str> + Hash + Eq,
+        V: AsRef<str>,
+        S: BuildHasher,
+    {
+        self.interp(text, &|template| map.get(template).map(AsRef::as_ref))
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], appending the result to a
+    /// caller-provided buffer instead of allocating a new String.
+    ///
+    /// The buffer is not cleared first, so call `out.clear()` to reuse it for a fresh result or
+    /// leave it as is to accumulate output across calls. If an error is returned, `out` is left
+    /// as it was before the call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world".to_string()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let mut out = String::new();
+    /// for text in ["Hello 'name", "Goodbye 'name"] {
+    ///     out.clear();
+    ///     text_interpolator.interp_into(text, &map, &mut out).unwrap();
+    ///     assert!(out.ends_with("world"));
+    /// }
+    /// ```
+    pub fn interp_into<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+        out: &mut String,
+    ) -> Result<(), InterpError> {
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut CallState::default(),
+            Sink::String(out),
+        )
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], writing the result to
+    /// anything implementing [`fmt::Write`], such as the formatter of a `Display` impl.
+    ///
+    /// Substitutions are recased, pluralized, and trimmed after they are written, so each word
+    /// is built in a buffer first, and handed to out as soon as the words after it can no longer
+    /// change it. Output written before a failure is not rolled back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fmt;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// struct Greeting<'a>(&'a str);
+    ///
+    /// impl fmt::Display for Greeting<'_> {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         let map = |template: &str| match template {
+    ///             "name" => Some(self.0),
+    ///             _ => None,
+    ///         };
+    ///         TextInterpolator::default()
+    ///             .interp_fmt("Hello 'name!", &map, f)
+    ///             .map_err(|_| fmt::Error)
+    ///     }
+    /// }
+    ///
+    /// assert_eq!("Hello world!", Greeting("world").to_string());
+    /// ```
+    pub fn interp_fmt<'m, S: Into<Substitution<'m>>, W: fmt::Write + ?Sized>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+        mut out: &mut W,
+    ) -> Result<(), InterpError> {
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut CallState::default(),
+            Sink::Writer(&mut out),
+        )
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], but borrows the input
+    /// instead of allocating when no substitutions occur.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world".to_string()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let literal = text_interpolator.interp_cow("Hello there", &map).unwrap();
+    /// assert!(matches!(literal, Cow::Borrowed("Hello there")));
+    ///
+    /// let substituted = text_interpolator.interp_cow("Hello 'name", &map).unwrap();
+    /// assert!(matches!(substituted, Cow::Owned(_)));
+    /// assert_eq!("Hello world", substituted);
+    /// ```
+    pub fn interp_cow<'t, 'm, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &'t str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<Cow<'t, str>, InterpError> {
+        if self.is_literal_text(text) {
+            return Ok(Cow::Borrowed(text));
+        }
+        if self.contains_conditional(text) {
+            return self.interp(text, map).map(Cow::Owned);
+        }
+
+        let map = ignore_args(map);
+
+        self.with_hook(&mut CallState::default(), |text_interpolator, state| {
+            let mut output = String::new();
+            let mut pieces = text_interpolator.split_word_indices(text);
+            // Length of the separators directly before the current word
+            let mut separator_len = 0;
+
+            // Nothing is written until the first word that changes, then the text before it is
+            // copied and the rest is interpolated as usual
+            while let Some((offset, is_word, item)) = pieces.next() {
+                if !is_word {
+                    separator_len = item.len();
+                    continue;
+                }
+
+                state.word_span = offset..offset + item.len();
+
+                let changed = text_interpolator.interp_word(item, &map, state, &mut output)?
+                    || match (text_interpolator.unescape)(item) {
+                        Some(unescaped) => {
+                            output.push_str(unescaped);
+                            true
+                        }
+                        None => false,
+                    };
+
+                if changed {
+                    output.insert_str(0, &text[..offset]);
+
+                    let mut collapse = Collapse {
+                        separator_start: (separator_len > 0).then(|| offset - separator_len),
+                        empty_word: None,
+                    };
+                    collapse.word(offset, &output);
+                    text_interpolator.interp_pieces(
+                        &mut pieces,
+                        &OffsetMap::default(),
+                        &map,
+                        state,
+                        &mut output,
+                        &mut collapse,
+                        None,
+                    )?;
+                    collapse.finish(&mut output);
+
+                    return Ok(Cow::Owned(output));
+                }
+
+                separator_len = 0;
+            }
+
+            Ok(Cow::Borrowed(text))
+        })
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`] with a map that can
+    /// mutate its own state, such as a counter or a record of earlier picks.
+    ///
+    /// The map is called once per lookup in the order templates are expanded, nested ones
+    /// included, so state it keeps carries over from one substitution to the next.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let mut players = 0;
+    /// let mut map = |template: &str| match template {
+    ///     "team" => Some("'player and 'player".to_string()),
+    ///     "player" => {
+    ///         players += 1;
+    ///         Some(format!("player {players}"))
+    ///     }
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator.interp_mut("'team, 'player", &mut map).unwrap();
+    ///
+    /// assert_eq!("player 1 and player 2, player 3", output);
+    /// ```
+    pub fn interp_mut<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &mut impl FnMut(&str) -> Option<S>,
+    ) -> Result<String, InterpError> {
+        // The map is never called again before a call to it returns
+        let map = RefCell::new(map);
+
+        self.interp(text, &|template| (map.borrow_mut())(template))
+    }
+
+    /// Interpolates an owned text in place, replacing its contents with the output.
+    ///
+    /// Text that has nothing to substitute or unescape is left untouched without allocating,
+    /// as with [`TextInterpolator::interp_cow`], which suits mostly pass-through content. On
+    /// error the text is left as it was.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let mut text = String::from("Hello 'name");
+    /// text_interpolator.interp_in_place(&mut text, &map).unwrap();
+    ///
+    /// assert_eq!("Hello world", text);
+    /// ```
+    pub fn interp_in_place<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &mut String,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<(), InterpError> {
+        if let Cow::Owned(output) = self.interp_cow(text, map)? {
+            *text = output;
+        }
+
+        Ok(())
+    }
+
+    /// Interpolates each line of text independently, rejoining the results with the line
+    /// endings of the input.
+    ///
+    /// Empty lines and a trailing newline are kept as they are, and so are `\r\n` endings,
+    /// which are never interpolated as part of their line, so they round-trip exactly even
+    /// when mixed with `\n`. Since interp already preserves
+    /// whitespace the output matches it, but the lines are processed one at a time so each is
+    /// its own unit of work. [`TextInterpolator::max_output_len`] still limits the whole
+    /// output rather than each line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let output = text_interpolator
+    ///     .interp_lines("Hello 'name\n\nGoodbye 'name\n", &map)
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello world\n\nGoodbye world\n", output);
+    /// ```
+    pub fn interp_lines<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<String, InterpError> {
+        let map = ignore_args(map);
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        let mut offset = 0;
+
+        for line in text.split_inclusive('\n') {
+            let content = line
+                .strip_suffix("\r\n")
+                .or_else(|| line.strip_suffix('\n'))
+                .unwrap_or(line);
+
+            // Every line starts from a fresh state, but max_output_len still applies to the
+            // whole output since it is counted from the start of it
+            let mut state = CallState::default();
+            self.with_hook(&mut state, |text_interpolator, state| {
+                text_interpolator.interp_nested(content, &map, state, &mut output, None)
+            })
+            .map_err(|err| err.offset_span(offset))?;
+            output.push_str(&line[content.len()..]);
+            self.check_output_len(&state, &output)?;
+            offset += line.len();
+        }
+
+        Ok(output)
+    }
+
+    /// Interpolates many texts in one go, each into a String of its own presized with
+    /// [`TextInterpolator::capacity_hint`], see [`TextInterpolator::interp_batch_with`] to reuse
+    /// a single buffer instead.
+    ///
+    /// Stops at the first text that fails to interpolate, returning its index along with the
+    /// error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world"),
+    ///     "loop" => Some("'loop"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let outputs = text_interpolator
+    ///     .interp_batch(&["Hello 'name", "Goodbye 'name"], &map)
+    ///     .unwrap();
+    /// assert_eq!(vec!["Hello world", "Goodbye world"], outputs);
+    ///
+    /// let (index, _) = text_interpolator
+    ///     .interp_batch(&["Hello 'name", "'loop"], &map)
+    ///     .unwrap_err();
+    /// assert_eq!(1, index);
+    /// ```
+    pub fn interp_batch<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        texts: &[&str],
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<Vec<String>, (usize, InterpError)> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(index, text)| self.interp(text, map).map_err(|err| (index, err)))
+            .collect()
+    }
+
+    /// Interpolates many texts in one go, reusing a single buffer across all of them, and
+    /// passes each output to f along with the index of its text, for callers that write the
+    /// outputs somewhere instead of keeping them.
+    ///
+    /// Stops at the first text that fails to interpolate, returning its index along with the
+    /// error, in which case f has been called for every text before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let mut page = String::new();
+    /// text_interpolator
+    ///     .interp_batch_with(&["Hello 'name", "Goodbye 'name"], &map, |index, output| {
+    ///         page.push_str(&format!("{index}: {output}\n"));
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!("0: Hello world\n1: Goodbye world\n", page);
+    /// ```
+    pub fn interp_batch_with<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        texts: &[&str],
+        map: &impl Fn(&str) -> Option<S>,
+        mut f: impl FnMut(usize, &str),
+    ) -> Result<(), (usize, InterpError)> {
+        let mut buffer = String::new();
+
+        for (index, text) in texts.iter().enumerate() {
+            buffer.clear();
+            self.interp_into(text, map, &mut buffer)
+                .map_err(|err| (index, err))?;
+
+            f(index, &buffer);
+        }
+
+        Ok(())
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], also returning the names
+    /// of templates that had no substitution, in the order they were first encountered.
+    ///
+    /// Templates left unresolved inside nested substitutions are reported as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world".to_string()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let (output, unresolved) = text_interpolator
+    ///     .interp_collecting("Hello 'name, 'nmae and 'greeting", &map)
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello world, 'nmae and 'greeting", output);
+    /// assert_eq!(vec!["nmae", "greeting"], unresolved);
+    /// ```
+    pub fn interp_collecting<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<(String, Vec<String>), InterpError> {
+        let mut state = CallState {
+            unresolved: Some(Vec::new()),
+            ..Default::default()
+        };
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut state,
+            Sink::String(&mut output),
+        )?;
+
+        Ok((output, state.unresolved.unwrap_or_default()))
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], also returning the total
+    /// number of substitutions performed, including nested ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name".to_string()),
+    ///     "name" => Some("world".to_string()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let (output, count) = text_interpolator
+    ///     .interp_counted("'greeting and 'greeting 'missing", &map)
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello world and Hello world 'missing", output);
+    /// assert_eq!(4, count);
+    /// ```
+    pub fn interp_counted<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<(String, usize), InterpError> {
+        let mut state = CallState::default();
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut state,
+            Sink::String(&mut output),
+        )?;
+
+        Ok((output, state.substitutions))
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], also returning the number
+    /// of template words left in the output, for re-interpolating a whole document pass after
+    /// pass until it converges.
+    ///
+    /// Templates are left over when they are unresolved and kept, deferred with `'!name`, or
+    /// written by a [`Literal`] substitution. Running passes until none remain, or until a pass
+    /// no longer changes the text, reaches a fixed point even when templates are only defined
+    /// in terms of one another across passes, as when the map or the dictionary behind it is
+    /// updated between them. A bound on the number of passes guards against templates that are
+    /// never defined or keep being deferred. Escaped words are unescaped by every pass, so a
+    /// word meant to stay literal has to be escaped once more for each pass after the first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "title" => Some("'!chapter 'number"),
+    ///     "chapter" => Some("Chapter"),
+    ///     "number" => Some("'!one"),
+    ///     "one" => Some("1"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let mut text = String::from("'title 'missing");
+    /// for _ in 0..10 {
+    ///     let (output, remaining) = text_interpolator.interp_pass(&text, &map).unwrap();
+    ///     let converged = remaining == 0 || output == text;
+    ///     text = output;
+    ///     if converged {
+    ///         break;
+    ///     }
+    /// }
+    ///
+    /// assert_eq!("Chapter 1 'missing", text);
+    /// ```
+    pub fn interp_pass<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<(String, usize), InterpError> {
+        let output = self.interp(text, map)?;
+        let remaining = self.leftover_templates(&output);
+
+        Ok((output, remaining))
+    }
+
+    /// Re-interpolates text pass after pass, as with [`TextInterpolator::interp_pass`], until
+    /// it converges or max_passes have run, returning the last output and whether it converged.
+    ///
+    /// Text has converged once no templates are left in it, or once a pass no longer changes
+    /// it, as when the templates left are undefined and kept. Passes stop without converging
+    /// when the output repeats one from an earlier pass, since text flipping between states
+    /// would never settle, and when max_passes is reached first. Text without templates is
+    /// returned as it is, without unescaping it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "title" => Some("'!chapter 'number"),
+    ///     "chapter" => Some("Chapter"),
+    ///     "number" => Some("'!one"),
+    ///     "one" => Some("1"),
+    ///     "ping" => Some("'!pong"),
+    ///     "pong" => Some("'!ping"),
+    ///     _ => None,
+    /// };
+    ///
+    /// assert_eq!(
+    ///     ("Chapter 1 'missing".to_string(), true),
+    ///     text_interpolator.interp_to_fixpoint("'title 'missing", &map, 10).unwrap()
+    /// );
+    /// assert_eq!(
+    ///     ("'ping".to_string(), false),
+    ///     text_interpolator.interp_to_fixpoint("'ping", &map, 10).unwrap()
+    /// );
+    /// ```
+    pub fn interp_to_fixpoint<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+        max_passes: usize,
+    ) -> Result<(String, bool), InterpError> {
+        let mut seen = BTreeSet::new();
+        let mut text = text.to_string();
+        let mut remaining = self.leftover_templates(&text);
+
+        for _ in 0..max_passes {
+            if remaining == 0 {
+                break;
+            }
+
+            let (output, left) = self.interp_pass(&text, map)?;
+            if output == text {
+                return Ok((text, true));
+            }
+
+            seen.insert(core::mem::replace(&mut text, output));
+            if seen.contains(&text) {
+                return Ok((text, false));
+            }
+            remaining = left;
+        }
+
+        Ok((text, remaining == 0))
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], also returning
+    /// [`Stats`] about the expansion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{Stats, TextInterpolator};
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let (output, stats) = text_interpolator
+    ///     .interp_with_stats("'greeting, 'name!", &map)
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello world, world!", output);
+    /// assert_eq!(
+    ///     Stats {
+    ///         substitutions: 3,
+    ///         max_depth_reached: 2,
+    ///         unique_templates: 2,
+    ///         output_len: 19,
+    ///     },
+    ///     stats
+    /// );
+    /// ```
+    pub fn interp_with_stats<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<(String, Stats), InterpError> {
+        let mut state = CallState {
+            substituted_templates: Some(BTreeSet::new()),
+            ..Default::default()
+        };
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut state,
+            Sink::String(&mut output),
+        )?;
+
+        let stats = Stats {
+            substitutions: state.substitutions,
+            max_depth_reached: state.max_depth_reached,
+            unique_templates: state.substituted_templates.map_or(0, |set| set.len()),
+            output_len: output.len(),
+        };
+        Ok((output, stats))
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], but performs at most
+    /// max_subs substitutions, including nested ones, and leaves every template after that as
+    /// it is written. Returns the output and the number of substitutions performed.
+    ///
+    /// This keeps previews of templates that expand to huge texts cheap, since the map isn't
+    /// called for templates past the limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let (output, count) = text_interpolator
+    ///     .interp_limited("'greeting and 'greeting", &map, 1)
+    ///     .unwrap();
+    ///
+    /// assert_eq!("Hello 'name and 'greeting", output);
+    /// assert_eq!(1, count);
+    /// ```
+    pub fn interp_limited<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+        max_subs: usize,
+    ) -> Result<(String, usize), InterpError> {
+        let mut state = CallState {
+            max_substitutions: Some(max_subs),
+            ..Default::default()
+        };
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut state,
+            Sink::String(&mut output),
+        )?;
+
+        Ok((output, state.substitutions))
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], but only substitutes
+    /// templates whose name is in allowed, for pipelines that interpolate in stages.
+    ///
+    /// Every other template is left exactly as written, even if map could resolve it, and
+    /// isn't affected by [`TextInterpolator::strict`] or [`TextInterpolator::on_unresolved`],
+    /// so a later pass can substitute it. This also applies to templates inside substitutions,
+    /// and to the rest of a word after a template that is left, as in `'later'now`.
+    /// A name written with a case directive, such as `'Noun`, is allowed when its lowercase
+    /// form is.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let allowed = HashSet::from(["greeting".to_string()]);
+    /// let output = text_interpolator.interp_subset("'greeting, 'name!", &map, &allowed).unwrap();
+    ///
+    /// assert_eq!("Hello 'name, 'name!", output);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn interp_subset<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+        allowed: &HashSet<String>,
+    ) -> Result<String, InterpError> {
+        let mut state = CallState {
+            allowed: Some(allowed.iter().cloned().collect()),
+            ..Default::default()
+        };
+        let mut output = String::with_capacity(self.output_capacity(text.len()));
+
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut state,
+            Sink::String(&mut output),
+        )?;
+
+        Ok(output)
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], returning the output as
+    /// segments that keep track of which parts were substituted.
+    ///
+    /// Each template in the input produces one [`Segment::Substituted`] holding its fully
+    /// resolved value, while its prefix, suffix, and the surrounding text are merged into
+    /// [`Segment::Literal`] segments. Unresolved templates are literal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{Segment, TextInterpolator};
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world".to_string()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let segments = text_interpolator.interp_segments("Hello 'name!", &map).unwrap();
+    ///
+    /// assert_eq!(
+    ///     vec![
+    ///         Segment::Literal("Hello ".to_string()),
+    ///         Segment::Substituted {
+    ///             template: "name".to_string(),
+    ///             value: "world".to_string(),
+    ///         },
+    ///         Segment::Literal("!".to_string()),
+    ///     ],
+    ///     segments
+    /// );
+    /// ```
+    pub fn interp_segments<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<Vec<Segment>, InterpError> {
+        let mut output = String::new();
+        let mut state = CallState {
+            spans: Some(Vec::new()),
+            ..Default::default()
+        };
+        self.with_hook(&mut state, |text_interpolator, state| {
+            text_interpolator.interp_nested(text, &ignore_args(map), state, &mut output, None)
+        })?;
+        let spans = state.spans.unwrap_or_default();
+
+        let mut segments = Vec::new();
+        let mut copied = 0;
+
+        for (template, span, _) in spans {
+            // A trailing empty substitution takes the separators before it along
+            let span = span.start.min(output.len())..span.end.min(output.len());
+
+            if span.start > copied {
+                segments.push(Segment::Literal(output[copied..span.start].to_string()));
+            }
+
+            segments.push(Segment::Substituted {
+                template,
+                value: output[span.clone()].to_string(),
+            });
+
+            copied = span.end;
+        }
+
+        if copied < output.len() {
+            segments.push(Segment::Literal(output[copied..].to_string()));
+        }
+
+        Ok(segments)
+    }
+
+    /// Interpolates text the same way as [`TextInterpolator::interp`], returning the template
+    /// split of every word of the input along with the fully resolved value of its template.
+    ///
+    /// Words are split the same way as [`TextInterpolator::split_all`], keeping the prefix and
+    /// suffix of each template apart rather than flattening them into literal text as
+    /// [`TextInterpolator::interp_segments`] does. The value is None for words without a
+    /// template and for templates that didn't resolve. Templates adjacent to the split's in
+    /// the same word are substituted as usual, but only the value of the first is returned.
+    /// Conditionals are split as they are written, so templates in the body of one get a value
+    /// only if its condition holds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "name" => Some("world"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let splits = text_interpolator
+    ///     .interp_splits("('greeting)! 'missing.", &map)
+    ///     .unwrap();
+    ///
+    /// let (split, value) = &splits[0];
+    /// assert_eq!(("(", "greeting", ")!"), (split.prefix, split.template, split.suffix));
+    /// assert_eq!(Some("Hello world"), value.as_deref());
+    ///
+    /// let (split, value) = &splits[1];
+    /// assert_eq!(("missing", "."), (split.template, split.suffix));
+    /// assert_eq!(None, *value);
+    /// ```
+    pub fn interp_splits<'t, 'm, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &'t str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<Vec<(TemplateSplit<'t>, Option<String>)>, InterpError> {
+        let mut output = String::new();
+        let mut state = CallState {
+            spans: Some(Vec::new()),
+            ..Default::default()
+        };
+        self.interp_call(
+            text,
+            &ignore_args(map),
+            &mut state,
+            Sink::String(&mut output),
+        )?;
+        let spans = state.spans.unwrap_or_default();
+
+        let words: Vec<Range<usize>> = self
+            .split_word_indices(text)
+            .filter(|&(_, is_word, _)| is_word)
+            .map(|(offset, _, word)| offset..offset + word.len())
+            .collect();
+
+        Ok(words
+            .into_iter()
+            .map(|word| {
+                let template_split = (self.extract_template)(&text[word.clone()]);
+                let value = spans
+                    .iter()
+                    .find(|(template, _, start)| {
+                        *start == word.start && *template == template_split.template
+                    })
+                    .map(|(_, span, _)| {
+                        // A trailing empty substitution takes the separators before it along
+                        output[span.start.min(output.len())..span.end.min(output.len())].to_string()
+                    });
+
+                (template_split, value)
+            })
+            .collect())
+    }
+
+    /// Interpolates text into sink with the given bookkeeping.
+    ///
+    /// A String is left unchanged on error, while a writer keeps whatever it was handed before
+    /// the error.
+    fn interp_call<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &mut CallState,
+        sink: Sink<'_>,
+    ) -> Result<(), InterpError> {
+        match sink {
+            Sink::String(out) => {
+                let start_len = out.len();
+                state.output_start = start_len;
+
+                let result = self.with_hook(state, |text_interpolator, state| {
+                    text_interpolator.interp_nested(text, map, state, out, None)
+                });
+
+                if result.is_err() {
+                    out.truncate(start_len);
+                }
+
+                result
+            }
+            Sink::Writer(writer) => self.with_hook(state, |text_interpolator, state| {
+                let mut buffer = String::new();
+                text_interpolator.interp_nested(
+                    text,
+                    map,
+                    state,
+                    &mut buffer,
+                    Some(&mut *writer),
+                )?;
+
+                writer.write_str(&buffer).map_err(InterpError::Write)
+            }),
+        }
+    }
+
+    /// Runs f with the on_substitute hook moved into state, so the engine can call it while
+    /// only borrowing the interpolator.
+    fn with_hook<T>(
+        &mut self,
+        state: &mut CallState,
+        f: impl FnOnce(&Self, &mut CallState) -> T,
+    ) -> T {
+        state.on_substitute = self.on_substitute.take();
+        let lent = LentHook {
+            text_interpolator: self,
+            state,
+        };
+
+        f(lent.text_interpolator, lent.state)
+    }
+
+    fn interp_nested<'m, S: Into<Substitution<'m>>>(
+        &self,
+        text: &str,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &mut CallState,
+        output: &mut String,
+        writer: Option<&mut dyn fmt::Write>,
+    ) -> Result<(), InterpError> {
+        if self.is_literal_text(text) {
+            output.push_str(text);
+            return self.check_output_len(state, output);
+        }
+
+        let mut offsets = OffsetMap::default();
+        let text =
+            self.expand_conditionals_mapped(Cow::Borrowed(text), map, state, 1, &mut offsets)?;
+        let mut collapse = Collapse::default();
+
+        self.interp_pieces(
+            self.split_word_indices(&text),
+            &offsets,
+            map,
+            state,
+            output,
+            &mut collapse,
+            writer,
+        )?;
+        collapse.finish(output);
+
+        Ok(())
+    }
+
+    /// Interpolates pieces of text as split by [`TextInterpolator::split_word_indices`] into
+    /// output, where offsets maps the text back to the input to report spans against.
+    ///
+    /// With a writer, output is handed to it and cleared after every word that leaves nothing
+    /// to be collapsed, so only the text that can still change is buffered.
+    #[allow(clippy::too_many_arguments)]
+    fn interp_pieces<'a, 'm, S: Into<Substitution<'m>>>(
+        &self,
+        pieces: impl Iterator<Item = (usize, bool, &'a str)>,
+        offsets: &OffsetMap,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &mut CallState,
+        output: &mut String,
+        collapse: &mut Collapse,
+        mut writer: Option<&mut dyn fmt::Write>,
+    ) -> Result<(), InterpError> {
+        for (offset, is_word, item) in pieces {
+            if !is_word {
+                collapse.separator(item, output);
+            } else {
+                state.word_span = offsets.input_span(offset..offset + item.len());
+                let mark = output.len();
+                if !self.interp_word(item, map, state, output)? {
+                    self.push_literal_word(item, output);
+                }
+                collapse.word(mark, output);
+            }
+
+            self.check_output_len(state, output)?;
+
+            if let Some(writer) = writer.as_mut().filter(|_| collapse.is_settled()) {
+                writer.write_str(output).map_err(InterpError::Write)?;
+                state.flushed += output.len();
+                output.clear();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails if the output written so far by the current call is longer than
+    /// [`TextInterpolator::max_output_len`].
+    fn check_output_len(&self, state: &CallState, output: &str) -> Result<(), InterpError> {
+        match self.max_output_len {
+            Some(limit)
+                if (state.flushed + output.len()).saturating_sub(state.output_start) > limit =>
+            {
+                Err(InterpError::OutputTooLarge { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Capacity to reserve for the output of an input of len bytes, see
+    /// [`TextInterpolator::capacity_hint`].
+    fn output_capacity(&self, len: usize) -> usize {
+        // Output is usually at least as long as the input
+        len.saturating_mul(self.capacity_hint.max(1))
+    }
+
+    /// Splits text into words with the interpolator's separators, see [`split_words`].
+    fn split_words<'a>(&'a self, text: &'a str) -> impl Iterator<Item = (bool, &'a str)> {
+        split_words(text, &*self.is_separator)
+    }
+
+    /// Splits text into words the same way as [`TextInterpolator::split_words`], also yielding
+    /// the byte offset of each piece.
+    fn split_word_indices<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = (usize, bool, &'a str)> {
+        let mut offset = 0;
+
+        self.split_words(text).map(move |(is_word, item)| {
+            let start = offset;
+            offset += item.len();
+            (start, is_word, item)
+        })
+    }
+
+    /// Whether text can't contain a template or an escaped word since it lacks the marker.
+    fn is_literal_text(&self, text: &str) -> bool {
+        self.marker.is_some_and(|marker| !text.contains(marker))
+    }
+
+    /// Number of template words in text, counting deferred ones such as `'!name` as well since
+    /// a later pass expands them.
+    fn leftover_templates(&self, text: &str) -> usize {
+        let deferred = self.split_all(text).filter(|split| split.deferred).count();
+
+        self.template_spans(text).len() + deferred
+    }
+
+    /// Pushes text that will not be interpolated, unescaping each of its words.
+    fn push_literal(&self, text: &str, output: &mut String) {
+        if self.is_literal_text(text) {
+            output.push_str(text);
+            return;
+        }
+
+        for (is_word, item) in self.split_words(text) {
+            if is_word {
+                self.push_literal_word(item, output);
+            } else {
+                output.push_str(item);
+            }
+        }
+    }
+
+    fn push_literal_word(&self, item: &str, output: &mut String) {
+        output.push_str((self.unescape)(item).unwrap_or(item));
+    }
+
+    /// Substitutes a single word of the input into output, returning false without writing
+    /// anything if the word has no substitution.
+    ///
+    /// Nested templates are resolved with an explicit stack of pending substitutions rather than
+    /// recursion, so arbitrarily deep nesting can't overflow the call stack.
+    fn interp_word<'m, S: Into<Substitution<'m>>>(
+        &self,
+        item: &str,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &mut CallState,
+        output: &mut String,
+    ) -> Result<bool, InterpError> {
+        let frame = match self.step_word(item, false, map, state, 0, output)? {
+            WordStep::Literal => return Ok(false),
+            WordStep::Substituted => return Ok(true),
+            WordStep::Nested(frame) => frame,
+        };
+
+        let mut stack = vec![frame];
+
+        loop {
+            self.check_output_len(state, output)?;
+
+            let depth = stack.len();
+            let Some(frame) = stack.last_mut() else {
+                break;
+            };
+
+            let start = frame.pos;
+            let Some((is_word, len)) = self
+                .split_words(&frame.text[start..])
+                .next()
+                .map(|(is_word, piece)| (is_word, piece.len()))
+            else {
+                // The substitution has been fully written, so its template can be reentered
+                let frame = stack.pop().expect("stack is not empty");
+                frame.collapse.finish(output);
+                state.exit_template();
+                self.finish_substitution(output, frame.start, frame.case, frame.plural);
+                if let Some(template) = frame.memo_template {
+                    state
+                        .memo
+                        .insert(template, output[frame.start..].to_string());
+                }
+                if let Some(template) = &frame.span_template {
+                    state.record_span(template, frame.start..output.len());
+                }
+
+                // The suffix is part of the word the template was found in
+                match self.step_word(&frame.suffix, true, map, state, stack.len(), output)? {
+                    WordStep::Nested(frame) => stack.push(frame),
+                    _ => {
+                        if let Some(parent) = stack.last_mut() {
+                            parent.collapse.word(parent.word_mark, output);
+                        }
+                    }
+                }
+                continue;
+            };
+
+            frame.pos += len;
+            let piece = &frame.text[start..start + len];
+
+            if !is_word {
+                frame.collapse.separator(piece, output);
+                continue;
+            }
+
+            let mark = output.len();
+            match self.step_word(piece, false, map, state, depth, output)? {
+                WordStep::Literal => {
+                    self.push_literal_word(piece, output);
+                    frame.collapse.word(mark, output);
+                }
+                WordStep::Substituted => frame.collapse.word(mark, output),
+                WordStep::Nested(child) => {
+                    frame.word_mark = mark;
+                    stack.push(child);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Substitutes the template in item, then every template directly after it in the rest of
+    /// the word, as in `'greeting'punctuation`, until one of them needs a nested frame.
+    ///
+    /// If adjacent is true, item is the rest of a word after a substituted template, and only
+    /// templates that resolve are substituted in it, everything else there is written as is.
+    fn step_word<'m, S: Into<Substitution<'m>>>(
+        &self,
+        item: &str,
+        adjacent: bool,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &mut CallState,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<WordStep<'m>, InterpError> {
+        let mut rest = item;
+        let mut adjacent = adjacent;
+
+        loop {
+            if adjacent && rest.is_empty() {
+                return Ok(WordStep::Substituted);
+            }
+
+            let step = self.begin_template(rest, adjacent, map, state, depth, output)?;
+            self.check_output_len(state, output)?;
+
+            match step {
+                TemplateStep::Unresolved if adjacent => {
+                    output.push_str(rest);
+                    return Ok(WordStep::Substituted);
+                }
+                TemplateStep::Unresolved => return Ok(WordStep::Literal),
+                TemplateStep::Substituted { suffix } => {
+                    rest = suffix;
+                    adjacent = true;
+                }
+                TemplateStep::Nested(frame) => return Ok(WordStep::Nested(frame)),
+            }
+        }
+    }
+
+    /// Starts substituting the template in a word found at the given nesting depth.
+    ///
+    /// If the substitution contains no templates it is written out completely, otherwise the
+    /// prefix is written and the rest is returned as a frame to be processed by the caller. The
+    /// suffix is never written, it is either returned or kept in the frame.
+    fn begin_template<'w, 'm, S: Into<Substitution<'m>>>(
+        &self,
+        item: &'w str,
+        adjacent: bool,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &mut CallState,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<TemplateStep<'w, 'm>, InterpError> {
+        let template_split = (self.extract_template)(item);
+
+        if template_split.deferred {
+            output.push_str(template_split.prefix);
+            output.push_str(template_split.suffix);
+            return Ok(TemplateStep::Substituted { suffix: "" });
+        }
+
+        if !self.is_allowed(template_split.template, state) {
+            return Ok(TemplateStep::Unresolved);
+        }
+
+        if self.is_rejected(template_split.template) {
+            // Unknown text after a template is left as it is, the same as when it doesn't resolve
+            if adjacent && !self.delimited {
+                return Ok(TemplateStep::Unresolved);
+            }
+
+            return Err(InterpError::UnknownTemplate {
+                template: template_split.template.to_string(),
+                span: state.word_span.clone(),
+            });
+        }
+
+        // The same template may be pluralized differently or get different arguments each time,
+        // so counted templates and ones with arguments aren't memoized
+        let memoize =
+            self.memoize && template_split.count.is_none() && template_split.args.is_none();
+
+        if memoize {
+            if let Some(value) = state.memo.get(template_split.template) {
+                if depth >= self.max_depth {
+                    return Err(InterpError::MaxDepthExceeded { depth: depth + 1 });
+                }
+
+                output.push_str(template_split.prefix);
+                let start = output.len();
+                output.push_str(value);
+                state.substituted(template_split.template, &output[start..], depth + 1);
+                if depth == 0 {
+                    state.record_span(template_split.template, start..output.len());
+                }
+
+                return Ok(TemplateStep::Substituted {
+                    suffix: template_split.suffix,
+                });
+            }
+        }
+
+        let sticky = self.sticky_resolved(&template_split, state);
+        let from_sticky = sticky.is_some();
+
+        let Resolved {
+            substitute,
+            name,
+            case,
+            from_map,
+            literal,
+        } = match sticky {
+            Some(resolved) => resolved,
+            None => self.resolve(&template_split, map, depth + 1)?,
+        };
+
+        // Only substitutions from the map are memoized, defaults depend on the word
+        let memo_template = (memoize && from_map).then(|| template_split.template.to_string());
+
+        let Some(substitute) = substitute else {
+            // Unresolved text after a template is usually punctuation such as the 's of a
+            // possessive rather than a template, unless templates are delimited
+            if adjacent && !self.delimited {
+                return Ok(TemplateStep::Unresolved);
+            }
+
+            if self.strict && self.max_depth > 0 && !template_split.template.is_empty() {
+                return Err(InterpError::UndefinedTemplate {
+                    template: template_split.template.to_string(),
+                    span: state.word_span.clone(),
+                });
+            }
+
+            if let Some(unresolved) = &mut state.unresolved {
+                if !template_split.template.is_empty()
+                    && !unresolved
+                        .iter()
+                        .any(|name| name == template_split.template)
+                {
+                    unresolved.push(template_split.template.to_string());
+                }
+            }
+
+            let replacement = match &self.on_unresolved {
+                _ if self.max_depth == 0 || template_split.template.is_empty() => None,
+                UnresolvedAction::Keep => None,
+                UnresolvedAction::StripMarker => Some(template_split.template),
+                UnresolvedAction::Remove => Some(""),
+                UnresolvedAction::Replace(replacement) => Some(replacement.as_str()),
+            };
+
+            return match replacement {
+                Some(replacement) => {
+                    output.push_str(template_split.prefix);
+                    output.push_str(replacement);
+                    Ok(TemplateStep::Substituted {
+                        suffix: template_split.suffix,
+                    })
+                }
+                None if self.delimited && self.contains_template(template_split.suffix) => {
+                    // Kept as written so the templates after it are still substituted
+                    let end = item.len() - template_split.suffix.len();
+                    output.push_str(&item[..end]);
+                    Ok(TemplateStep::Substituted {
+                        suffix: template_split.suffix,
+                    })
+                }
+                None => Ok(TemplateStep::Unresolved),
+            };
+        };
+
+        if self.sticky && from_map && !from_sticky && template_split.args.is_none() {
+            let substitution = Substitution {
+                text: Cow::Owned(substitute.to_string()),
+                literal,
+                template: !literal,
+            };
+            state.sticky.insert(name.to_string(), substitution);
+        }
+
+        if depth >= self.max_depth {
+            return Err(InterpError::MaxDepthExceeded { depth: depth + 1 });
+        }
+
+        let plural = match template_split.count {
+            Some(count) => self.is_plural(count, map, state)?,
+            None => false,
+        };
+
+        // Substitutions are never expanded without recursion, so they can't loop
+        if self.recursive && self.detect_loops {
+            state.enter_template(&name, depth + 1)?;
+        }
+
+        state.substituted(template_split.template, &substitute, depth + 1);
+
+        output.push_str(template_split.prefix);
+
+        let recurse = self.recursive && !literal && (self.recurse)(&name);
+        let substitute = match recurse {
+            true => self.expand_conditionals(substitute, map, state, depth + 1)?,
+            false => substitute,
+        };
+        if recurse && self.contains_template(&substitute) {
+            return Ok(TemplateStep::Nested(Frame {
+                text: substitute,
+                pos: 0,
+                span_template: (depth == 0 && state.spans.is_some())
+                    .then(|| template_split.template.to_string()),
+                suffix: template_split.suffix.to_string(),
+                start: output.len(),
+                memo_template,
+                case,
+                plural,
+                collapse: Collapse::default(),
+                word_mark: 0,
+            }));
+        }
+
+        let start = output.len();
+        if recurse {
+            self.push_literal(&substitute, output);
+        } else {
+            output.push_str(&substitute);
+        }
+        self.finish_substitution(output, start, case, plural);
+        if let Some(template) = memo_template {
+            state.memo.insert(template, output[start..].to_string());
+        }
+        if depth == 0 {
+            state.record_span(template_split.template, start..output.len());
+        }
+        if self.recursive && self.detect_loops {
+            state.exit_template();
+        }
+
+        Ok(TemplateStep::Substituted {
+            suffix: template_split.suffix,
+        })
+    }
+
+    /// Returns the substitute picked earlier in the call for a template when sticky is
+    /// enabled, looking it up the same way [`TextInterpolator::resolve`] does.
+    fn sticky_resolved<'t, 'm>(
+        &self,
+        template_split: &TemplateSplit<'t>,
+        state: &CallState,
+    ) -> Option<Resolved<'t, 'm>> {
+        if !self.sticky || template_split.args.is_some() {
+            return None;
+        }
+
+        let template = template_split.template;
+        let resolved = |name, case, substitution: &Substitution| Resolved {
+            substitute: Some(Cow::Owned(substitution.text.to_string())),
+            name,
+            case,
+            from_map: true,
+            literal: substitution.literal,
+        };
+
+        let case = match self.case_directives {
+            true => Case::of(template),
+            false => Case::AsIs,
+        };
+        let lookup = match self.case_insensitive_lookup {
+            true => Cow::Owned(template.to_lowercase()),
+            false => Cow::Borrowed(template),
+        };
+
+        // Looping aliases are left for resolve to report
+        let name = self.unalias(&lookup, 0).ok()?;
+        if let Some(substitute) = state.sticky.get(name) {
+            let (name, case) = match self.case_insensitive_lookup {
+                true => (Cow::Owned(name.to_string()), case),
+                false if name == template => (Cow::Borrowed(template), Case::AsIs),
+                false => (Cow::Owned(name.to_string()), Case::AsIs),
+            };
+            return Some(resolved(name, case, substitute));
+        }
+
+        if self.case_insensitive_lookup || case == Case::AsIs {
+            return None;
+        }
+
+        let lowercase = template.to_lowercase();
+        let name = self.unalias(&lowercase, 0).ok()?.to_string();
+        let substitute = state.sticky.get(&name)?;
+        Some(resolved(Cow::Owned(name), case, substitute))
+    }
+
+    /// Looks up the substitution for an extracted template, following aliases, by its lowercase
+    /// name when lookups ignore case, or falling back to the lowercase name when a case
+    /// directive applies, and then to the default. Depth is that
+    /// of the substitution, reported if the aliases loop.
+    fn resolve<'t, 'm, S: Into<Substitution<'m>>>(
+        &self,
+        template_split: &TemplateSplit<'t>,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        depth: usize,
+    ) -> Result<Resolved<'t, 'm>, NestedTemplateLoopError> {
+        let mut resolved = Resolved {
+            substitute: None,
+            name: Cow::Borrowed(template_split.template),
+            case: Case::AsIs,
+            from_map: false,
+            literal: false,
+        };
+
+        // A max depth of 0 means no substitution at all, and words without a template never
+        // have one
+        if self.max_depth == 0 || template_split.template.is_empty() {
+            return Ok(resolved);
+        }
+
+        let args: Vec<&str> = match template_split.args {
+            Some(args) if !args.trim().is_empty() => args.split(',').map(str::trim).collect(),
+            _ => Vec::new(),
+        };
+
+        let lookup = match self.case_insensitive_lookup {
+            true => Cow::Owned(template_split.template.to_lowercase()),
+            false => Cow::Borrowed(template_split.template),
+        };
+        let name = self.unalias(&lookup, depth)?;
+        if name != template_split.template {
+            resolved.name = Cow::Owned(name.to_string());
+        }
+        let mut found = map(&resolved.name, &args);
+
+        if self.case_insensitive_lookup {
+            if self.case_directives {
+                resolved.case = Case::of(template_split.template);
+            }
+        } else if found.is_none() && self.case_directives {
+            resolved.case = Case::of(template_split.template);
+            if resolved.case != Case::AsIs {
+                let lowercase = template_split.template.to_lowercase();
+                resolved.name = Cow::Owned(self.unalias(&lowercase, depth)?.to_string());
+                found = map(&resolved.name, &args);
+            }
+        }
+
+        match found {
+            Some(substitute) => {
+                let substitution = substitute.into();
+                resolved.substitute = Some(substitution.text);
+                resolved.literal =
+                    substitution.literal || (self.literal_by_default && !substitution.template);
+                resolved.from_map = true;
+            }
+            None => {
+                resolved.substitute = template_split
+                    .default
+                    .map(|default| Cow::Owned(default.to_string()));
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Checks whether a count calls for the plural, looking it up in map unless it is a number.
+    fn is_plural<'m, S: Into<Substitution<'m>>>(
+        &self,
+        count: &str,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &CallState,
+    ) -> Result<bool, InterpError> {
+        let value = match map(count, &[]) {
+            Some(value) => value.into().text.trim().parse::<f64>(),
+            None => match count.parse::<f64>() {
+                Ok(value) => Ok(value),
+                Err(_) if self.strict => {
+                    return Err(InterpError::UndefinedTemplate {
+                        template: count.to_string(),
+                        span: state.word_span.clone(),
+                    })
+                }
+                Err(err) => Err(err),
+            },
+        };
+
+        Ok(value.is_ok_and(|value| value != 1.0))
+    }
+
+    /// Applies the pluralization and case requested by a template to its fully resolved
+    /// substitution, which is everything in output from start on.
+    fn finish_substitution(&self, output: &mut String, start: usize, case: Case, plural: bool) {
+        if self.trim_substitutions {
+            self.trim_whitespace(output, start);
+        }
+
+        if plural {
+            let plural = self.pluralizer.pluralize(&output[start..]);
+            output.truncate(start);
+            output.push_str(&plural);
+        }
+
+        case.apply(output, start);
+    }
+
+    /// Trims the separators around the text in output from start on and shortens every run of
+    /// separators inside it to its first one, see [`TextInterpolator::trim_substitutions`].
+    fn trim_whitespace(&self, output: &mut String, start: usize) {
+        let mut trimmed = String::with_capacity(output.len() - start);
+
+        for (is_word, item) in self.split_words(output[start..].trim_matches(&*self.is_separator)) {
+            match is_word {
+                true => trimmed.push_str(item),
+                // A line ending is shortened as a whole rather than leaving its \r behind
+                false if item.starts_with("\r\n") => trimmed.push_str("\r\n"),
+                false => trimmed.extend(item.chars().next()),
+            }
+        }
+
+        output.truncate(start);
+        output.push_str(&trimmed);
+    }
+
+    /// Does nothing, since interpolation no longer keeps any state between calls.
+    ///
+    /// This used to clear bookkeeping left behind by a map that panicked. That bookkeeping, such
+    /// as the templates being expanded, sticky picks, and memoized substitutions, is now held by
+    /// each call and dropped with it, even when the map panics, so the next call starts from a
+    /// clean slate on its own. Settings such as the seeded rng and the registered templates and
+    /// aliases are configuration rather than state, and are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    ///
+    /// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     text_interpolator.interp("'name", &|_| -> Option<String> { panic!("map failed") })
+    /// }));
+    /// assert!(result.is_err());
+    ///
+    /// // No reset needed
+    /// let output = text_interpolator.interp("'name", &|_| Some("world".to_string()));
+    /// assert_eq!("world", output.unwrap());
+    /// ```
+    #[deprecated(
+        note = "interpolation keeps its state per call, so there is nothing to reset between calls"
+    )]
+    pub fn reset(&mut self) {}
+
+    pub fn contains_template(&self, text: &str) -> bool {
+        !self.is_literal_text(text)
+            && self
+                .split_words(text)
+                .any(|(is_word, item)| is_word && (self.is_template)(item))
+    }
+}
+
+/// The rng and memoization setting of a TextInterpolator, taken out for the duration of a
+/// call that turns memoization off or draws from the rng inside a map.
+///
+/// Both are put back when it is dropped, so they are restored even when a map panics.
+struct Suspended<'a> {
+    text_interpolator: &'a mut TextInterpolator,
+    memoize: bool,
+    rng: RefCell<RngFn>,
+}
+
+impl<'a> Suspended<'a> {
+    fn new(text_interpolator: &'a mut TextInterpolator) -> Self {
+        Suspended {
+            memoize: core::mem::replace(&mut text_interpolator.memoize, false),
+            rng: RefCell::new(core::mem::replace(
+                &mut text_interpolator.rng,
+                Box::new(|| 0),
+            )),
+            text_interpolator,
+        }
+    }
+}
+
+impl Drop for Suspended<'_> {
+    fn drop(&mut self) {
+        self.text_interpolator.memoize = self.memoize;
+        core::mem::swap(&mut self.text_interpolator.rng, self.rng.get_mut());
+    }
+}
+
+/// A TextInterpolator whose on_substitute hook has been moved into the state of a call.
+///
+/// The hook is moved back when it is dropped, so it is restored even when a map panics.
+struct LentHook<'a> {
+    text_interpolator: &'a mut TextInterpolator,
+    state: &'a mut CallState,
+}
+
+impl Drop for LentHook<'_> {
+    fn drop(&mut self) {
+        self.text_interpolator.on_substitute = self.state.on_substitute.take();
+    }
+}
+
+impl CallState {
+    /// Records where the substitution of a template from the input ended up in the output.
+    fn record_span(&mut self, template: &str, span: Range<usize>) {
+        if let Some(spans) = &mut self.spans {
+            spans.push((template.to_string(), span, self.word_span.start));
+        }
+    }
+
+    /// Counts a substitution of a template found at the given depth, reporting it to the
+    /// on_substitute hook and recording it for [`TextInterpolator::explain`].
+    fn substituted(&mut self, template: &str, value: &str, depth: usize) {
+        self.substitutions += 1;
+        self.max_depth_reached = self.max_depth_reached.max(depth);
+        if let Some(templates) = &mut self.substituted_templates {
+            if !templates.contains(template) {
+                templates.insert(template.to_string());
+            }
+        }
+        if let Some(on_substitute) = &mut self.on_substitute {
+            on_substitute(template, value, depth);
+        }
+        if let Some(expansions) = &mut self.expansions {
+            expansions.push((template.to_string(), value.to_string(), depth));
+        }
+    }
+
+    /// Marks a template as being expanded, failing if it is already part of the current
+    /// expansion.
+    fn enter_template(
+        &mut self,
+        template: &str,
+        depth: usize,
+    ) -> Result<(), NestedTemplateLoopError> {
+        if !self.template_set.insert(template.to_string()) {
+            let start = self
+                .template_chain
+                .iter()
+                .position(|name| name == template)
+                .unwrap_or(0);
+
+            let mut chain = self.template_chain[start..].to_vec();
+            chain.push(template.to_string());
+
+            return Err(NestedTemplateLoopError { chain, depth });
+        }
+
+        self.template_chain.push(template.to_string());
+
+        Ok(())
+    }
+
+    /// Marks the most recently entered template as fully expanded.
+    fn exit_template(&mut self) {
+        if let Some(template) = self.template_chain.pop() {
+            self.template_set.remove(&template);
+        }
+    }
+}
+
+/// Outcome of starting to substitute a single word.
+enum WordStep<'m> {
+    /// The word has no substitution and nothing was written.
+    Literal,
+    /// The substitution was written out completely.
+    Substituted,
+    /// The substitution contains templates of its own and still has to be processed.
+    Nested(Frame<'m>),
+}
+
+/// Outcome of starting to substitute the template in a word.
+enum TemplateStep<'w, 'm> {
+    /// The template has no substitution and nothing was written.
+    Unresolved,
+    /// The substitution was written out completely, leaving the suffix of the word.
+    Substituted { suffix: &'w str },
+    /// The substitution contains templates of its own and still has to be processed.
+    Nested(Frame<'m>),
+}
+
+/// A substitution whose text is still being interpolated.
+struct Frame<'m> {
+    text: Cow<'m, str>,
+    /// Byte offset of the next unprocessed piece of text.
+    pos: usize,
+    /// Template to record the span of once complete, for templates found in the input itself.
+    span_template: Option<String>,
+    suffix: String,
+    /// Byte offset in the output where the resolved substitution starts.
+    start: usize,
+    /// Template to memoize the resolved substitution under once it is complete.
+    memo_template: Option<String>,
+    /// Case to apply to the resolved substitution once it is complete.
+    case: Case,
+    /// Whether to pluralize the resolved substitution once it is complete.
+    plural: bool,
+    collapse: Collapse,
+    /// Byte offset in the output where the word waiting on a nested frame starts.
+    word_mark: usize,
+}
+
+/// Tracks the separators around words that interpolate to nothing, so an empty substitution
+/// such as a blank `'middlename` doesn't leave a doubled or dangling separator behind.
+///
+/// The run of separators after an empty word is dropped, or the run before it when the word
+/// ends the text or the run after it holds a line break. A run holding a line break is never
+/// dropped, so an empty word on a line of its own leaves an empty line.
+#[derive(Default)]
+struct Collapse {
+    /// Start in the output of the last run of separators, if nothing but empty words has been
+    /// written since.
+    separator_start: Option<usize>,
+    /// Set right after an empty word, holding separator_start at that point.
+    empty_word: Option<Option<usize>>,
+}
+
+impl Collapse {
+    /// Writes a run of separators, unless it directly follows an empty word.
+    fn separator(&mut self, item: &str, output: &mut String) {
+        if let Some(before) = self.empty_word.take() {
+            if !has_line_break(item) {
+                return;
+            }
+
+            // This run ends the line, so the word takes the run before it instead
+            if let Some(start) = before.filter(|&start| !has_line_break(&output[start..])) {
+                output.truncate(start);
+            }
+        }
+
+        self.separator_start = Some(output.len());
+        output.push_str(item);
+    }
+
+    /// Records that a word has been written to output from mark on.
+    fn word(&mut self, mark: usize, output: &str) {
+        if output.len() == mark {
+            self.empty_word = Some(self.separator_start);
+        } else {
+            self.separator_start = None;
+            self.empty_word = None;
+        }
+    }
+
+    /// Whether nothing written so far can still be dropped.
+    fn is_settled(&self) -> bool {
+        self.separator_start.is_none() && self.empty_word.is_none()
+    }
+
+    /// Drops the separators before an empty word at the end of the text.
+    fn finish(self, output: &mut String) {
+        if let Some(Some(start)) = self.empty_word {
+            if !has_line_break(&output[start..]) {
+                output.truncate(start);
+            }
+        }
+    }
+}
+
+/// Whether a run of separators ends a line.
+fn has_line_break(separators: &str) -> bool {
+    separators.contains(['\n', '\r'])
+}
+
+/// The substitution found for a template.
+struct Resolved<'t, 'm> {
+    substitute: Option<Cow<'m, str>>,
+    /// Name the substitution was looked up under, which differs from the template when a case
+    /// directive applies.
+    name: Cow<'t, str>,
+    case: Case,
+    /// Whether the substitution came from the map rather than a default.
+    from_map: bool,
+    /// Whether the map asked for the substitution to be written verbatim.
+    literal: bool,
+}
+
+/// Case requested by the way a template name is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    AsIs,
+    Capitalized,
+    Upper,
+}
+
+impl Case {
+    /// A name starting with an uppercase letter is capitalized, one with at least two letters
+    /// that are all uppercase is uppercased.
+    fn of(name: &str) -> Case {
+        let mut letters = name.chars().filter(|ch| ch.is_alphabetic());
+
+        match letters.next() {
+            Some(first) if first.is_uppercase() => {
+                let mut rest = letters.peekable();
+                if rest.peek().is_some() && rest.all(char::is_uppercase) {
+                    Case::Upper
+                } else {
+                    Case::Capitalized
+                }
+            }
+            _ => Case::AsIs,
+        }
+    }
+
+    /// Applies the case to everything in output from start on.
+    fn apply(self, output: &mut String, start: usize) {
+        let recased = match self {
+            Case::AsIs => return,
+            Case::Upper => output[start..].to_uppercase(),
+            Case::Capitalized => case::capitalize(&output[start..]),
+        };
+
+        output.truncate(start);
+        output.push_str(&recased);
+    }
+}
+
+/// Creates the default rng, which without `std` has no entropy source and starts from a fixed
+/// seed instead.
+fn entropy_rng() -> RngFn {
+    #[cfg(feature = "std")]
+    let mut rng = random::Rng::from_entropy();
+    #[cfg(not(feature = "std"))]
+    let mut rng = random::Rng::new(0);
+
+    Box::new(move || rng.next_usize())
+}
+
+/// Adapts a map that only takes template names to the engine, which also passes arguments.
+fn ignore_args<'a, S>(
+    map: &'a impl Fn(&str) -> Option<S>,
+) -> impl Fn(&str, &[&str]) -> Option<S> + 'a {
+    move |template, _| map(template)
+}
+
+/// Splits text into words and the runs of separators between them, yielding every piece in
+/// order along with whether it is a word. Joining the pieces reproduces the text exactly.
+fn split_words<'a>(
+    text: &'a str,
+    is_separator: &'a dyn Fn(char) -> bool,
+) -> impl Iterator<Item = (bool, &'a str)> {
+    let mut rest = text;
+
+    core::iter::from_fn(move || {
+        let is_word = !is_separator(rest.chars().next()?);
+        let end = rest
+            .find(|ch: char| is_separator(ch) == is_word)
+            .unwrap_or(rest.len());
+
+        let (piece, remainder) = rest.split_at(end);
+        rest = remainder;
+
+        Some((is_word, piece))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    fn map_template(template: &str) -> Option<String> {
+        match template {
+            "verb" => Some(["run", "fall", "fly", "swim"][0].to_string()),
+            "noun" => Some(["person", "place", "thing"][1].to_string()),
+            "adj" => Some(["funny", "interesting", "aggrivating"][2].to_string()),
+            "sentence" => Some(
+                [
+                    "A 'adj 'noun should never 'verb..",
+                    "I've never seen someone 'verb with a 'noun before.",
+                    "You are too 'adj to be 'adj..",
+                ][1]
+                .to_string(),
+            ),
+            "paragraph" => Some(["'sentence 'sentence 'sentence"][0].to_string()),
+            "infinite" => Some("'infinite".to_string()),
+            "nonexistantnest" => Some("'nothing".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolate_non_templated_text() {
+        let mut interpolator = TextInterpolator::default();
+
+        let text: String = String::from(
+            "This is an example of a basic input with no templates to be substituted.",
+        );
+        let interpolated_text = interpolator.interp(&text, &map_template);
+
+        dbg!(&interpolated_text);
+
+        assert_eq!(&text, &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn interpolate_preserves_whitespace() {
+        let mut interpolator = TextInterpolator::default();
+
+        let text: String = String::from("  hello\t\t'noun\n\nfoo   'verb\n");
+        let interpolated_text = interpolator.interp(&text, &map_template);
+
+        assert_eq!(
+            "  hello\t\tplace\n\nfoo   run\n",
+            &interpolated_text.unwrap()
+        );
+    }
+
+    #[test]
+    fn interpolate_preserves_whitespace_in_substitutions() {
+        let mut interpolator = TextInterpolator::default();
+
+        let interpolated_text = interpolator.interp("'spaced", &|template: &str| match template {
+            "spaced" => Some("a\t'noun  b".to_string()),
+            _ => map_template(template),
+        });
+
+        assert_eq!("a\tplace  b", &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn interpolate_lines_preserves_line_structure() {
+        let mut interpolator = TextInterpolator::default();
+
+        for text in [
+            "'noun\n\n 'verb \n",
+            "\n'noun",
+            "'noun",
+            "",
+            "\n\n",
+            "\r\n'noun\r",
+        ] {
+            let interpolated_text = interpolator.interp_lines(text, &map_template).unwrap();
+
+            assert_eq!(
+                text.replace("'noun", "place").replace("'verb", "run"),
+                interpolated_text
+            );
+        }
+    }
+
+    #[test]
+    fn crlf_line_endings_round_trip() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "noun" => Some("place"),
+            "blank" => Some(""),
+            "lines" => Some("one\r\ntwo\nthree"),
+            _ => None,
+        };
+        let text = "'noun\r\n\r\n'noun 'blank\n'lines\r\nend 'blank\r\n";
+
+        assert_eq!(
+            "place\r\n\r\nplace\none\r\ntwo\nthree\r\nend\r\n",
+            interpolator.interp_lines(text, &map).unwrap()
+        );
+        assert_eq!(
+            interpolator.interp_lines(text, &map).unwrap(),
+            interpolator.interp(text, &map).unwrap()
+        );
+
+        interpolator.trim_substitutions = true;
+        let map = |template: &str| match template {
+            "lines" => Some("\r\none\r\n\r\ntwo\n\nthree \r\n"),
+            _ => None,
+        };
+        assert_eq!(
+            "a one\r\ntwo\nthree\r\nb",
+            interpolator.interp("a 'lines\r\nb", &map).unwrap()
+        );
+    }
+
+    #[test]
+    fn interpolate_into_appends_to_buffer() {
+        let mut interpolator = TextInterpolator::default();
+        let mut out = String::from("Story: ");
+
+        interpolator
+            .interp_into("A 'adj 'noun.", &map_template, &mut out)
+            .unwrap();
+        assert_eq!("Story: A aggrivating place.", &out);
+
+        assert!(interpolator
+            .interp_into(" 'infinite", &map_template, &mut out)
+            .is_err());
+        assert_eq!("Story: A aggrivating place.", &out);
+    }
+
+    #[test]
+    fn interpolate_bytes_matches_interp() {
+        let mut interpolator = TextInterpolator::default();
+        let text = "Ünïcode 'noun, 'Adj 'nonexistant «'noun»";
+
+        assert_eq!(
+            interpolator
+                .interp(text, &map_template)
+                .unwrap()
+                .into_bytes(),
+            interpolator
+                .interp_bytes(text.as_bytes(), &map_template)
+                .unwrap()
+        );
+
+        // A multi-byte character cut short
+        let truncated = &"'noun é".as_bytes()[..7];
+        let err = interpolator
+            .interp_bytes(truncated, &map_template)
+            .unwrap_err();
+        assert!(matches!(err, InterpError::InvalidUtf8(err) if err.valid_up_to() == 6));
+    }
+
+    #[test]
+    fn interpolate_fmt_writes_to_any_writer() {
+        struct Failing;
+
+        impl fmt::Write for Failing {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let mut interpolator = TextInterpolator::default();
+        let text = "'sentence 'Noun 'nonexistant";
+
+        let mut out = String::from("> ");
+        interpolator
+            .interp_fmt(text, &map_template, &mut out)
+            .unwrap();
+        assert_eq!(
+            format!("> {}", interpolator.interp(text, &map_template).unwrap()),
+            out
+        );
+
+        assert!(matches!(
+            interpolator.interp_fmt(text, &map_template, &mut Failing),
+            Err(InterpError::Write(fmt::Error))
+        ));
+        assert!(matches!(
+            interpolator.interp_fmt("'infinite", &map_template, &mut Failing),
+            Err(InterpError::NestedTemplateLoop(_))
+        ));
+
+        // Words are handed over as soon as they are settled
+        let mut out = String::new();
+        assert!(matches!(
+            interpolator.interp_fmt("fine 'infinite", &map_template, &mut out),
+            Err(InterpError::NestedTemplateLoop(_))
+        ));
+        assert_eq!("fine", out);
+        let map = |template: &str| match template {
+            "blank" => Some(String::new()),
+            _ => map_template(template),
+        };
+        for text in [
+            "a 'blank  b 'blank\n'blank c 'blank",
+            "'blank 'noun 'Adj 'paragraph",
+        ] {
+            let mut out = String::new();
+            interpolator.interp_fmt(text, &map, &mut out).unwrap();
+            assert_eq!(interpolator.interp(text, &map).unwrap(), out);
+        }
+    }
+
+    #[test]
+    fn interpolate_positional_indexes_into_args() {
+        let mut interpolator = TextInterpolator {
+            on_unresolved: UnresolvedAction::Remove,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "Ada, Ada and Grace '1",
+            interpolator
+                .interp_positional("'0, '0 and 'one'2 ''1", &["Ada", "", "Grace"])
+                .unwrap()
+        );
+        assert_eq!(
+            "a c",
+            interpolator.interp_positional("a '3 c", &["b"]).unwrap()
+        );
+
+        interpolator.strict = true;
+        assert!(matches!(
+            interpolator.interp_positional("'-1", &["b"]),
+            Err(InterpError::UndefinedTemplate { template, .. }) if template == "-1"
+        ));
+    }
+
+    #[test]
+    fn interpolate_cow_borrows_non_templated_text() {
+        let mut interpolator = TextInterpolator::default();
+
+        let text = "This is an example of a basic input with no templates to be substituted.";
+        let interpolated_text = interpolator.interp_cow(text, &map_template).unwrap();
+
+        assert!(matches!(interpolated_text, Cow::Borrowed(borrowed) if borrowed == text));
+    }
+
+    #[test]
+    fn interpolate_cow_matches_interp() {
+        let mut interpolator = TextInterpolator::default();
+
+        let text = "My\tStory:'paragraph...  The 'noun 'verb end ";
+        let interpolated_text = interpolator.interp_cow(text, &map_template).unwrap();
+
+        assert!(matches!(interpolated_text, Cow::Owned(_)));
+        assert_eq!(
+            interpolator.interp(text, &map_template).unwrap(),
+            interpolated_text
+        );
+    }
+
+    #[test]
+    fn interpolate_collecting_unresolved_templates() {
+        let mut interpolator = TextInterpolator::default();
+
+        let (interpolated_text, unresolved) = interpolator
+            .interp_collecting("'noun 'missing 'nonexistantnest 'missing", &map_template)
+            .unwrap();
+
+        assert_eq!("place 'missing 'nothing 'missing", &interpolated_text);
+        assert_eq!(vec!["missing", "nothing"], unresolved);
+    }
+
+    #[test]
+    fn interpolate_counted_includes_nested_substitutions() {
+        let mut interpolator = TextInterpolator::default();
+
+        let (_, count) = interpolator
+            .interp_counted("'paragraph 'missing", &map_template)
+            .unwrap();
+        assert_eq!(1 + 3 * 3, count);
+
+        let (_, count) = interpolator
+            .interp_counted("nothing here", &map_template)
+            .unwrap();
+        assert_eq!(0, count);
+    }
+
+    #[test]
+    fn interpolate_with_stats_describes_the_expansion() {
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            ..Default::default()
+        };
+
+        let (output, stats) = interpolator
+            .interp_with_stats("'paragraph 'Noun 'missing", &map_template)
+            .unwrap();
+        assert_eq!(
+            Stats {
+                // The sentence is expanded once and then reused
+                substitutions: 1 + 3 + 2 + 1,
+                max_depth_reached: 3,
+                unique_templates: 5,
+                output_len: output.len(),
+            },
+            stats
+        );
+
+        let (_, stats) = interpolator
+            .interp_with_stats("nothing 'missing", &map_template)
+            .unwrap();
+        assert_eq!(
+            Stats {
+                output_len: 16,
+                ..Default::default()
+            },
+            stats
+        );
+    }
+
+    #[test]
+    fn interp_limited_leaves_templates_past_the_limit() {
+        let mut interpolator = TextInterpolator {
+            strict: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            (
+                "I've never seen someone run with a 'noun before. 'sentence 'sentence".to_string(),
+                3
+            ),
+            interpolator
+                .interp_limited("'paragraph", &map_template, 3)
+                .unwrap()
+        );
+        assert_eq!(
+            ("'infinite 'missing".to_string(), 1),
+            interpolator
+                .interp_limited("'infinite 'missing", &map_template, 1)
+                .unwrap()
+        );
+        assert_eq!(
+            ("'noun".to_string(), 0),
+            interpolator
+                .interp_limited("'noun", &map_template, 0)
+                .unwrap()
+        );
+        assert_eq!(
+            interpolator
+                .interp_counted("'paragraph", &map_template)
+                .unwrap(),
+            interpolator
+                .interp_limited("'paragraph", &map_template, usize::MAX)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interp_static_matches_interp() {
+        let lookups = Cell::new(0);
+        let map = |template: &str| {
+            lookups.set(lookups.get() + 1);
+            match template {
+                "adj" => Some("funny"),
+                "noun" => Some("place"),
+                "escaped" => Some("''tis"),
+                "empty" => Some(""),
+                "nested" => Some("a 'noun"),
+                _ => None,
+            }
+        };
+        let mut interpolator = TextInterpolator::default();
+
+        assert_eq!(
+            "A funny place's 'tis, 'tis.",
+            interpolator
+                .interp_static("A 'adj 'noun's 'escaped, ''tis.", &map)
+                .unwrap()
+        );
+        assert_eq!(4, lookups.get());
+
+        for text in [
+            "'nested 'missing",
+            "'Noun 'ADJ 'noun#2 'missing|default",
+            "'adj 'empty 'noun",
+            "'adj'noun 'noun's",
+            "('!noun) 'noun'!adj",
+            "",
+        ] {
+            assert_eq!(
+                interpolator.interp(text, &map).unwrap(),
+                interpolator.interp_static(text, &map).unwrap()
+            );
+        }
+
+        interpolator.strict = true;
+        assert!(matches!(
+            interpolator.interp_static("'adj 'missing", &map),
+            Err(InterpError::UndefinedTemplate { .. })
+        ));
+    }
+
+    #[test]
+    fn template_spans_match_template_words() {
+        let text_interpolator = TextInterpolator::default();
+        let text = "\t'café's naïve ''tis\n'noun";
+
+        let spans: Vec<&str> = text_interpolator
+            .template_spans(text)
+            .into_iter()
+            .map(|span| &text[span])
+            .collect();
+
+        assert_eq!(vec!["'café's", "'noun"], spans);
+        assert!(text_interpolator.template_spans("no templates").is_empty());
+    }
+
+    #[test]
+    fn interpolator_is_shareable_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TextInterpolator>();
+
+        let shared = Arc::new(TextInterpolator::default());
+        let locked = Arc::new(Mutex::new(TextInterpolator::default()));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                let locked = Arc::clone(&locked);
+                thread::spawn(move || {
+                    let spans = shared.template_spans("A 'noun here");
+                    let output = locked.lock().unwrap().interp("'sentence", &map_template);
+                    (spans, output.unwrap())
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let (spans, output) = handle.join().unwrap();
+            assert_eq!(vec![2..7], spans);
+            assert_eq!("I've never seen someone run with a place before.", output);
+        }
+    }
+
+    #[test]
+    fn interp_ref_matches_interp_from_many_threads() {
+        let mut interpolator = TextInterpolator::default();
+        let text = "'paragraph 'nonexistantnest";
+        let expected = interpolator.interp(text, &map_template).unwrap();
+
+        let shared = Arc::new(interpolator);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    assert!(matches!(
+                        shared.interp_ref("'infinite", &map_template),
+                        Err(InterpError::NestedTemplateLoop(_))
+                    ));
+                    shared.interp_ref(text, &map_template).unwrap()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(expected, handle.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn case_directives_recase_substitutions() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "phrase" => Some("a 'noun, élan"),
+            "noun" => Some("place"),
+            "Verb" => Some("walk"),
+            "loop" => Some("'Loop"),
+            _ => None,
+        };
+
+        assert_eq!(
+            "Place PLACE A place, élan A PLACE, ÉLAN walk 'Missing Thing.",
+            interpolator
+                .interp(
+                    "'Noun 'NOUN 'Phrase 'PHRASE 'Verb 'Missing 'Other|thing.",
+                    &map
+                )
+                .unwrap()
+        );
+        assert!(matches!(
+            interpolator.interp("'loop", &map),
+            Err(InterpError::NestedTemplateLoop(_))
+        ));
+
+        interpolator.case_directives = false;
+        assert_eq!(
+            "'Noun place",
+            interpolator.interp("'Noun 'noun", &map).unwrap()
+        );
+    }
+
+    #[test]
+    fn counted_templates_are_pluralized() {
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            ..Default::default()
+        };
+        interpolator.pluralizer.irregular("cactus", "cacti");
+
+        let map = |template: &str| match template {
+            "pet" => Some("'adj mouse"),
+            "adj" => Some("small"),
+            "plant" => Some("cactus"),
+            "one" => Some("1"),
+            "many" => Some(" 12 "),
+            _ => None,
+        };
+
+        assert_eq!(
+            "1 small mouse, 12 small mice, 3 Cacti, NO CACTI and a cactus.",
+            interpolator
+                .interp(
+                    "1 'pet#one, 12 'pet#many, 3 'Plant#3, NO 'PLANT#0 and a 'plant#dunno.",
+                    &map
+                )
+                .unwrap()
+        );
+
+        interpolator.strict = true;
+        assert!(matches!(
+            interpolator.interp("'plant#dunno", &map),
+            Err(InterpError::UndefinedTemplate { template, .. }) if template == "dunno"
+        ));
+    }
+
+    #[test]
+    fn trim_substitutions_collapses_only_substituted_whitespace() {
+        let mut interpolator = TextInterpolator {
+            trim_substitutions: true,
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "color" => Some("  bright \t  'shade "),
+            "shade" => Some(" red\n"),
+            "blank" => Some("   "),
+            _ => None,
+        };
+
+        assert_eq!(
+            "A  bright red car's  paint,\n\nBright red.",
+            interpolator
+                .interp("A  'color car's  'blank paint,\n\n'Color'blank.", &map)
+                .unwrap()
+        );
+
+        interpolator.trim_substitutions = false;
+        assert_eq!(
+            "A    bright \t   red\n  car",
+            interpolator.interp("A  'color car", &map).unwrap()
+        );
+    }
+
+    #[test]
+    fn custom_separators_are_preserved() {
+        let mut interpolator = TextInterpolator {
+            is_separator: Box::new(|ch| ch == ','),
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "row" => Some("'noun,'adj"),
+            "noun" => Some("a place"),
+            "adj" => Some("funny"),
+            _ => None,
+        };
+        let text = "'noun,,'adj, 'noun,'row,";
+
+        assert_eq!(
+            "a place,,funny, a place,a place,funny,",
+            interpolator.interp(text, &map).unwrap()
+        );
+        assert_eq!(
+            vec!["noun", "adj", "row"],
+            interpolator.find_templates(text)
+        );
+        assert!(interpolator.contains_template("plain,'noun"));
+    }
+
+    #[test]
+    fn interp_batch_matches_interp() {
+        let mut interpolator = TextInterpolator::default();
+        let texts = ["'sentence", "", "'paragraph 'nonexistantnest", "'adj"];
+
+        let outputs = interpolator.interp_batch(&texts, &map_template).unwrap();
+
+        for (text, output) in texts.iter().zip(outputs) {
+            assert_eq!(interpolator.interp(text, &map_template).unwrap(), output);
+        }
+
+        assert!(matches!(
+            interpolator.interp_batch(&["'adj", "'verb", "'infinite"], &map_template),
+            Err((2, InterpError::NestedTemplateLoop(_)))
+        ));
+        assert_eq!(
+            Vec::<String>::new(),
+            interpolator.interp_batch(&[], &map_template).unwrap()
+        );
+
+        let mut streamed = Vec::new();
+        interpolator
+            .interp_batch_with(&texts, &map_template, |index, output| {
+                streamed.push((index, output.to_string()));
+            })
+            .unwrap();
+        assert_eq!(
+            interpolator
+                .interp_batch(&texts, &map_template)
+                .unwrap()
+                .into_iter()
+                .enumerate()
+                .collect::<Vec<_>>(),
+            streamed
+        );
+
+        let mut streamed = Vec::new();
+        assert!(matches!(
+            interpolator.interp_batch_with(
+                &["'adj", "'infinite", "'verb"],
+                &map_template,
+                |index, _| streamed.push(index)
+            ),
+            Err((1, InterpError::NestedTemplateLoop(_)))
+        ));
+        assert_eq!(vec![0], streamed);
+    }
+
+    #[test]
+    fn capacity_hint_presizes_output() {
+        let mut interpolator = TextInterpolator {
+            capacity_hint: 16,
+            ..Default::default()
+        };
+        let text = "'paragraph";
+
+        let hinted = interpolator.interp(text, &map_template).unwrap();
+        assert!(hinted.capacity() >= text.len() * 16);
+
+        interpolator.capacity_hint = 0;
+        assert_eq!(hinted, interpolator.interp(text, &map_template).unwrap());
+
+        interpolator.capacity_hint = usize::MAX;
+        assert_eq!("", interpolator.interp("", &map_template).unwrap());
+    }
+
+    #[test]
+    fn on_substitute_reports_every_substitution() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&fired);
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            on_substitute: Some(Box::new(move |template, value, depth| {
+                log.lock()
+                    .unwrap()
+                    .push((template.to_string(), value.to_string(), depth));
+            })),
+            ..Default::default()
+        };
+
+        interpolator
+            .interp("'sentence 'verb 'missing|x", &map_template)
+            .unwrap();
+
+        let entry =
+            |template: &str, value: &str, depth| (template.to_string(), value.to_string(), depth);
+        assert_eq!(
+            vec![
+                entry(
+                    "sentence",
+                    "I've never seen someone 'verb with a 'noun before.",
+                    1
+                ),
+                entry("verb", "run", 2),
+                entry("noun", "place", 2),
+                entry("verb", "run", 1),
+                entry("missing", "x", 1),
+            ],
+            *fired.lock().unwrap()
+        );
+
+        // The hook is kept when interpolation fails
+        fired.lock().unwrap().clear();
+        assert!(interpolator.interp("'infinite", &map_template).is_err());
+        assert!(interpolator.on_substitute.is_some());
+        interpolator.interp_cow("'noun", &map_template).unwrap();
+        assert_eq!(
+            vec![entry("infinite", "'infinite", 1), entry("noun", "place", 1)],
+            *fired.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn max_output_len_stops_expansion_bombs() {
+        let map = |template: &str| match template {
+            "bomb" => Some("'a 'a 'a 'a"),
+            "a" => Some("'b 'b 'b 'b"),
+            "b" => Some("'c 'c 'c 'c"),
+            "c" => Some("'d 'd 'd 'd"),
+            "d" => Some("boom boom boom boom"),
+            _ => None,
+        };
+        let mut interpolator = TextInterpolator {
+            max_output_len: Some(1000),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            interpolator.interp("'bomb", &map),
+            Err(InterpError::OutputTooLarge { limit: 1000 })
+        ));
+        assert!(interpolator.interp_ref("'bomb", &map).is_err());
+        assert!(interpolator.interp_cow("'bomb", &map).is_err());
+        assert!(matches!(interpolator.validate("'bomb", &map), Ok(())));
+
+        // Only what the call writes counts towards the limit
+        let mut out = "x".repeat(2000);
+        interpolator.interp_into("'c", &map, &mut out).unwrap();
+        assert_eq!(2000 + 79, out.len());
+
+        interpolator.max_output_len = Some(79);
+        assert_eq!(79, interpolator.interp("'c", &map).unwrap().len());
+        interpolator.max_output_len = Some(78);
+        assert!(interpolator.interp("'c", &map).is_err());
+
+        // The limit applies to all lines together
+        let lines = "0123456789\n0123456789\n0123456789";
+        interpolator.max_output_len = Some(15);
+        assert!(matches!(
+            interpolator.interp(lines, &map),
+            Err(InterpError::OutputTooLarge { limit: 15 })
+        ));
+        assert!(matches!(
+            interpolator.interp_lines(lines, &map),
+            Err(InterpError::OutputTooLarge { limit: 15 })
+        ));
+        interpolator.max_output_len = Some(32);
+        assert_eq!(lines, interpolator.interp_lines(lines, &map).unwrap());
+    }
+
+    #[test]
+    fn interp_args_passes_arguments_to_map() {
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            ..Default::default()
+        };
+        let map = |template: &str, args: &[&str]| match (template, args) {
+            ("pick", [first, ..]) => Some(format!("'{first}")),
+            ("pick", []) => Some("nothing".to_string()),
+            (template, []) => map_template(template),
+            _ => None,
+        };
+
+        assert_eq!(
+            "place, Run and nothing. 'pick(noun)",
+            interpolator
+                .interp_args(
+                    "'pick(noun,verb), 'Pick(verb) and 'pick(). ''pick(noun)",
+                    &map
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            "place run",
+            interpolator
+                .interp_args("'pick(noun) 'pick(verb)", &map)
+                .unwrap()
+        );
+        assert_eq!(
+            "place.",
+            interpolator
+                .interp("'noun(ignored).", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn words_without_templates_are_unchanged() {
+        let looked_up = RefCell::new(Vec::new());
+        let map = |template: &str| {
+            looked_up.borrow_mut().push(template.to_string());
+            map_template(template)
+        };
+        let text = "(adj) ' '' '(x) '- '. !? it's {noun} ''noun 'noun's.";
+
+        let mut interpolator = TextInterpolator::default();
+        assert_eq!(
+            "(adj) ' ' '(x) '- '. !? it's {noun} 'noun place's.",
+            interpolator.interp(text, &map).unwrap()
+        );
+
+        let mut interpolator = defaults::with_braces();
+        assert_eq!(
+            "(adj) ' '' '(x) '- '. !? it's place ''noun 'noun's.",
+            interpolator.interp(text, &map).unwrap()
+        );
+
+        assert_eq!(vec!["noun", "s", "noun"], *looked_up.borrow());
+    }
+
+    #[test]
+    fn empty_template_name_is_never_substituted() {
+        let map = |template: &str| match template {
+            "" => Some("oops".to_string()),
+            _ => map_template(template),
+        };
+        let text = "Plain words, '(x) 'noun's ''tis.";
+
+        let mut interpolator = TextInterpolator {
+            strict: true,
+            ..Default::default()
+        };
+        let interpolated_text = "Plain words, '(x) place's 'tis.";
+        assert_eq!(interpolated_text, interpolator.interp(text, &map).unwrap());
+        assert_eq!(
+            interpolated_text,
+            interpolator
+                .interp_static(text, &|template| match template {
+                    "" => Some("oops"),
+                    "noun" => Some("place"),
+                    _ => None,
+                })
+                .unwrap()
+        );
+        assert_eq!(Ok(()), interpolator.validate(text, &map));
+        assert!(matches!(
+            interpolator.interp_cow("Plain words", &map),
+            Ok(Cow::Borrowed("Plain words"))
+        ));
+    }
+
+    #[test]
+    fn text_without_marker_is_copied_as_is() {
+        let mut interpolator = TextInterpolator::default();
+        let text = "No templates in this text, $noun {noun}";
+
+        assert!(matches!(
+            interpolator.interp_cow(text, &map_template),
+            Ok(Cow::Borrowed(_))
+        ));
+        assert!(!interpolator.contains_template(text));
+        let with_marker = interpolator.interp(text, &map_template).unwrap();
+
+        interpolator.marker = None;
+        assert_eq!(
+            interpolator.interp(text, &map_template).unwrap(),
+            with_marker
+        );
+        assert_eq!(text, with_marker);
+
+        let mut interpolator = defaults::with_braces();
+        assert_eq!(
+            "'noun place",
+            interpolator.interp("'noun {noun}", &map_template).unwrap()
+        );
+    }
+
+    #[test]
+    fn trailing_separators_are_preserved() {
+        let mut interpolator = TextInterpolator::default();
+
+        for (text, interpolated_text) in [
+            ("", ""),
+            ("   ", "   "),
+            ("'noun ", "place "),
+            ("'noun\t\n", "place\t\n"),
+            (" 'noun", " place"),
+        ] {
+            assert_eq!(
+                interpolated_text,
+                interpolator.interp(text, &map_template).unwrap()
+            );
+            assert_eq!(
+                interpolated_text,
+                interpolator
+                    .interp_static(text, &|template| match template {
+                        "noun" => Some("place"),
+                        _ => None,
+                    })
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn empty_substitutions_collapse_separators() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "first" => Some("Ada"),
+            "middle" | "blank" => Some(""),
+            "last" => Some("Lovelace"),
+            "name" => Some("'first 'middle 'last"),
+            "tail" => Some("'last 'blank"),
+            "nothing" => Some("'blank 'blank"),
+            _ => None,
+        };
+
+        for (text, expected) in [
+            ("'first 'middle 'last", "Ada Lovelace"),
+            ("'middle 'last", "Lovelace"),
+            ("'first 'middle", "Ada"),
+            ("'middle", ""),
+            ("'first  'middle 'blank\n'last", "Ada\nLovelace"),
+            ("line1 'blank\nline2", "line1\nline2"),
+            ("'blank\nx", "\nx"),
+            ("line1 \n 'blank x", "line1 \n x"),
+            ("line1\n'blank", "line1\n"),
+            ("line1\n'blank\nline2", "line1\n\nline2"),
+            ("Dear 'name,", "Dear Ada Lovelace,"),
+            ("('middle) 'tail.", "() Lovelace."),
+            ("[ 'nothing ]", "[ ]"),
+            (" 'middle ", " "),
+        ] {
+            assert_eq!(
+                expected,
+                interpolator.interp(text, &map).unwrap(),
+                "{text:?}"
+            );
+            assert_eq!(expected, interpolator.interp_cow(text, &map).unwrap());
+            assert_eq!(expected, interpolator.interp_ref(text, &map).unwrap());
+            assert_eq!(expected, interpolator.interp_lines(text, &map).unwrap());
+        }
+
+        assert_eq!(
+            vec![
+                Segment::Substituted {
+                    template: "first".to_string(),
+                    value: "Ada".to_string(),
+                },
+                Segment::Substituted {
+                    template: "middle".to_string(),
+                    value: String::new(),
+                },
+            ],
+            interpolator
+                .interp_segments("'first 'middle", &map)
+                .unwrap()
+        );
+
+        interpolator.on_unresolved = UnresolvedAction::Remove;
+        assert_eq!(
+            "Hello world",
+            interpolator.interp("Hello 'missing world", &map).unwrap()
+        );
+    }
+
+    #[test]
+    fn recurse_false_writes_substitution_literally() {
+        let mut interpolator = TextInterpolator {
+            recurse: Box::new(|template| !matches!(template, "infinite" | "raw")),
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "raw" => Some("''tis 'noun".to_string()),
+            _ => map_template(template),
+        };
+
+        assert_eq!(
+            "'infinite 'Nothing ''TIS 'NOUN.",
+            interpolator
+                .interp("'infinite 'Nonexistantnest 'RAW.", &map)
+                .unwrap()
+        );
+        assert_eq!(Ok(()), interpolator.validate("'infinite 'raw", &map));
+    }
+
+    #[test]
+    fn non_recursive_interpolation_writes_substitutions_verbatim() {
+        let mut interpolator = TextInterpolator {
+            recursive: false,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "I've never seen someone 'verb with a 'noun before. 'infinite placeplace",
+            interpolator
+                .interp("'sentence 'infinite 'noun'noun", &map_template)
+                .unwrap()
+        );
+        assert_eq!(
+            interpolator
+                .interp("'sentence 'infinite", &map_template)
+                .unwrap(),
+            TextInterpolatorBuilder::default()
+                .recursive(false)
+                .build()
+                .interp("'sentence 'infinite", &map_template)
+                .unwrap()
+        );
+        assert_eq!(Ok(()), interpolator.validate("'infinite", &map_template));
+    }
+
+    #[test]
+    fn literal_substitutions_are_not_expanded() {
+        let mut interpolator = TextInterpolator {
+            sticky: true,
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "data" => Some(Literal("it's 'infinite, ''tis".to_string()).into()),
+            _ => map_template(template).map(Substitution::from),
+        };
+
+        assert_eq!(
+            "it's 'infinite, ''tis IT'S 'INFINITE, ''TIS place",
+            interpolator.interp("'data 'DATA 'noun", &map).unwrap()
+        );
+        assert_eq!(Ok(()), interpolator.validate("'data", &map));
+    }
+
+    #[test]
+    fn literal_by_default_only_expands_templates() {
+        let mut interpolator = TextInterpolator {
+            literal_by_default: true,
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "sentence" => Some(Template("'name said ''hi 'missing|'default").into()),
+            "name" => Some(Template("'user").into()),
+            "user" => Some(Substitution::from("'noun's 'infinite")),
+            "default" => Some(Substitution::from("''x")),
+            "infinite" => Some(Substitution::from("'infinite")),
+            _ => None,
+        };
+
+        let text = "'sentence 'user";
+        assert_eq!(
+            "'noun's 'infinite said 'hi ''x 'noun's 'infinite",
+            interpolator.interp(text, &map).unwrap()
+        );
+        assert_eq!(Ok(()), interpolator.validate(text, &map));
+        assert_eq!(
+            "'noun's 'infinite",
+            interpolator
+                .interp_static("'user", &|template| match template {
+                    "user" => Some("'noun's 'infinite"),
+                    _ => None,
+                })
+                .unwrap()
+        );
+
+        interpolator.literal_by_default = false;
+        assert!(matches!(
+            interpolator.interp(text, &map),
+            Err(InterpError::NestedTemplateLoop(_))
+        ));
+    }
+
+    #[test]
+    fn interp_in_place_only_replaces_changed_text() {
+        let mut interpolator = TextInterpolator::default();
+
+        let mut text = String::with_capacity(64);
+        text.push_str("Nothing to see here");
+        let buffer = text.as_ptr();
+        interpolator
+            .interp_in_place(&mut text, &map_template)
+            .unwrap();
+        assert_eq!("Nothing to see here", text);
+        assert_eq!(buffer, text.as_ptr());
+
+        let mut text = String::from("'sentence ''tis");
+        interpolator
+            .interp_in_place(&mut text, &map_template)
+            .unwrap();
+        assert_eq!(
+            interpolator
+                .interp("'sentence ''tis", &map_template)
+                .unwrap(),
+            text
+        );
+
+        let mut text = String::from("'adj 'infinite");
+        assert!(interpolator
+            .interp_in_place(&mut text, &map_template)
+            .is_err());
+        assert_eq!("'adj 'infinite", text);
+    }
+
+    #[test]
+    fn interp_mut_keeps_state_across_nested_expansions() {
+        let mut interpolator = TextInterpolator::default();
+        let mut picks: Vec<String> = Vec::new();
+        let mut map = |template: &str| {
+            let value = match template {
+                // Remembers the first noun so later ones agree with it
+                "noun" => picks
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "place".to_string()),
+                _ => map_template(template)?,
+            };
+            picks.push(value.clone());
+            Some(value)
+        };
+
+        let output = interpolator
+            .interp_mut("'noun 'sentence 'noun", &mut map)
+            .unwrap();
+
+        assert_eq!(
+            "place I've never seen someone run with a place before. place",
+            output
+        );
+        assert_eq!(5, picks.len());
+    }
+
+    #[test]
+    fn sticky_reuses_first_pick_in_a_call() {
+        let mut interpolator = TextInterpolatorBuilder::default()
+            .sticky(true)
+            .seed(3)
+            .build();
+        let map = |template: &str| match template {
+            "name" => Some((0..100).map(|n| format!("person{n}")).collect()),
+            "line" => Some(vec!["'name said".to_string(), "'name asked".to_string()]),
+            _ => None,
+        };
+
+        let output = interpolator
+            .interp_random("'line to 'name. 'Name#2 'line|x", &map)
+            .unwrap();
+        let words: Vec<&str> = output.split(' ').collect();
+
+        assert_eq!(7, words.len());
+        assert_eq!(words[0], words[3].trim_end_matches('.'));
+        assert_eq!(format!("{}s", words[0].replacen('p', "P", 1)), words[4]);
+        assert_eq!(words[0..2], words[5..7]);
+
+        // Each call picks afresh
+        let picks: HashSet<String> = (0..20)
+            .map(|_| interpolator.interp_random("'name", &map).unwrap())
+            .collect();
+        assert!(picks.len() > 1);
+
+        let mut count = 0;
+        let mut counter = |template: &str| match template {
+            "n" => {
+                count += 1;
+                Some(count.to_string())
+            }
+            _ => None,
+        };
+        assert_eq!(
+            "1 1 1",
+            interpolator.interp_mut("'n 'n 'n", &mut counter).unwrap()
+        );
+        interpolator.sticky = false;
+        assert_eq!(
+            "2 3 4",
+            interpolator.interp_mut("'n 'n 'n", &mut counter).unwrap()
+        );
+    }
+
+    #[test]
+    fn unresolved_action_applies_to_nested_templates() {
+        let mut interpolator = TextInterpolator {
+            on_unresolved: UnresolvedAction::Replace("?".to_string()),
+            ..Default::default()
+        };
+
+        let (output, unresolved) = interpolator
+            .interp_collecting(
+                "'nonexistantnest, 'missing's ''escaped plain",
+                &map_template,
+            )
+            .unwrap();
+
+        assert_eq!("?, ?'s 'escaped plain", output);
+        assert_eq!(vec!["nothing", "missing"], unresolved);
+
+        interpolator.max_depth = 0;
+        assert_eq!(
+            "'nonexistantnest",
+            interpolator
+                .interp("'nonexistantnest", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn interpolate_pass_counts_leftover_templates() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "later" => Some("'!noun and 'noun".into()),
+            "quoted" => Some(Literal("'noun").into()),
+            _ => map_template(template).map(Substitution::from),
+        };
+
+        let (output, remaining) = interpolator
+            .interp_pass("'later, ('missing). 'quoted", &map)
+            .unwrap();
+        assert_eq!("'noun and place, ('missing). 'noun", output);
+        assert_eq!(3, remaining);
+
+        let (output, remaining) = interpolator.interp_pass(&output, &map).unwrap();
+        assert_eq!("place and place, ('missing). place", output);
+        assert_eq!(1, remaining);
+
+        interpolator.on_unresolved = UnresolvedAction::Remove;
+        assert_eq!(
+            ("place and place, (). place".to_string(), 0),
+            interpolator.interp_pass(&output, &map).unwrap()
+        );
+
+        let deferring = |template: &str| match template {
+            "quoted" => Some(Literal("'!noun 'noun")),
+            _ => None,
+        };
+        assert_eq!(
+            ("'!noun 'noun".to_string(), 2),
+            interpolator.interp_pass("'quoted", &deferring).unwrap()
+        );
+    }
+
+    #[test]
+    fn interpolate_to_fixpoint_stops_at_repeats() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "a" => Some("'!b".to_string()),
+            "b" => Some("'!c".to_string()),
+            "c" => Some("'!a".to_string()),
+            "deep" => Some("'!noun".to_string()),
+            _ => map_template(template),
+        };
+
+        assert_eq!(
+            ("(place), 'missing".to_string(), true),
+            interpolator
+                .interp_to_fixpoint("('deep), 'missing", &map, 3)
+                .unwrap()
+        );
+        assert_eq!(
+            ("'a".to_string(), false),
+            interpolator.interp_to_fixpoint("'a", &map, 10).unwrap()
+        );
+        assert_eq!(
+            ("'c".to_string(), false),
+            interpolator.interp_to_fixpoint("'a", &map, 2).unwrap()
+        );
+        assert_eq!(
+            ("''tis 'noun".to_string(), false),
+            interpolator
+                .interp_to_fixpoint("''tis 'noun", &map, 0)
+                .unwrap()
+        );
+        assert_eq!(
+            ("''tis".to_string(), true),
+            interpolator.interp_to_fixpoint("''tis", &map, 5).unwrap()
+        );
+        assert_eq!(
+            ("place".to_string(), true),
+            interpolator.interp_to_fixpoint("'!noun", &map, 5).unwrap()
+        );
+        assert_eq!(
+            ("'noun".to_string(), false),
+            interpolator.interp_to_fixpoint("'!noun", &map, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn case_insensitive_lookup_keeps_the_written_case() {
+        let mut interpolator = TextInterpolator {
+            case_insensitive_lookup: true,
+            strict: true,
+            ..Default::default()
+        };
+        interpolator.add_alias("thing", "noun");
+        let looked_up = RefCell::new(Vec::new());
+        let map = |template: &str| {
+            looked_up.borrow_mut().push(template.to_string());
+            match template {
+                "Noun" => Some("wrong".to_string()),
+                "greeting" => Some("hello 'NAME".to_string()),
+                _ => map_template(template),
+            }
+        };
+
+        assert_eq!(
+            "Place PLACE Hello ADA Ada",
+            interpolator
+                .interp("'Noun 'THING 'Greeting 'Missing|'Name", &|template| {
+                    match template {
+                        "name" => Some("ada".to_string()),
+                        _ => map(template),
+                    }
+                })
+                .unwrap()
+        );
+        assert!(looked_up
+            .borrow()
+            .iter()
+            .all(|template| *template == template.to_lowercase()));
+
+        interpolator.sticky = true;
+        interpolator.case_directives = false;
+        assert_eq!(
+            "place place place",
+            interpolator.interp("'noun 'NOUN 'Thing", &map).unwrap()
+        );
+
+        interpolator.reject_unknown = true;
+        interpolator.register_templates(["noun".to_string()]);
+        assert_eq!("place", interpolator.interp("'NoUn", &map).unwrap());
+        assert!(matches!(
+            interpolator.interp("'Verb", &map),
+            Err(InterpError::UnknownTemplate { template, .. }) if template == "Verb"
+        ));
+    }
+
+    #[test]
+    fn interpolate_splits_pairs_words_with_their_value() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "blank" => Some(String::new()),
+            "punctuation" => Some("!".to_string()),
+            "premium" => Some("true".to_string()),
+            _ => map_template(template),
+        };
+
+        let splits = interpolator
+            .interp_splits(
+                "plain  'Noun's 'missing'punctuation 'verb'punctuation ''tis 'blank",
+                &map,
+            )
+            .unwrap();
+        let summary: Vec<(&str, &str, &str, Option<&str>)> = splits
+            .iter()
+            .map(|(split, value)| (split.prefix, split.template, split.suffix, value.as_deref()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("", "", "", None),
+                ("", "Noun", "'s", Some("Place")),
+                ("", "missing", "'punctuation", None),
+                ("", "verb", "'punctuation", Some("run")),
+                ("", "", "", None),
+                ("", "blank", "", Some("")),
+            ],
+            summary
+        );
+
+        let splits = interpolator
+            .interp_splits("'if(premium: Dear 'noun) 'adj 'if(missing: x) 'verb.", &map)
+            .unwrap();
+        let values: Vec<(&str, Option<&str>)> = splits
+            .iter()
+            .filter(|(split, _)| split.template != "if")
+            .map(|(split, value)| (split.template, value.as_deref()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("", None),
+                ("noun", Some("place")),
+                ("adj", Some("aggrivating")),
+                ("", None),
+                ("verb", Some("run")),
+            ],
+            values
+        );
+
+        assert!(matches!(
+            interpolator.interp_splits("'loop", &|_| Some("'loop")),
+            Err(InterpError::NestedTemplateLoop(_))
+        ));
+    }
+
+    #[test]
+    fn interpolate_borrowed_reads_from_the_store() {
+        let mut interpolator = TextInterpolator::default();
+        let store: Vec<(String, String)> = [
+            ("sentence", "A 'adj 'Noun."),
+            ("adj", "funny"),
+            ("noun", "place"),
+        ]
+        .map(|(template, value)| (template.to_string(), value.to_string()))
+        .into();
+        let map = |template: &str| {
+            store
+                .iter()
+                .find(|(key, _)| key == template)
+                .map(|(_, value)| value.as_str())
+        };
+
+        let output = interpolator.interp_borrowed("'sentence 'missing", &map);
+        assert_eq!("A funny Place. 'missing", output.unwrap());
+
+        interpolator.strict = true;
+        assert!(matches!(
+            interpolator.interp_borrowed("'missing", &map),
+            Err(InterpError::UndefinedTemplate { template, .. }) if template == "missing"
+        ));
+    }
+
+    #[test]
+    fn replaying_a_choice_log_reproduces_the_output() {
+        let mut interpolator = TextInterpolator::default();
+        interpolator.seed(9);
+        let map = |template: &str| match template {
+            "stanza" => Some(vec!["'line / 'line".to_string()]),
+            "line" => Some((0..50).map(|line| format!("'word.{line}")).collect()),
+            "word" => Some((0..50).map(|word| format!("w{word}")).collect()),
+            "empty" => Some(Vec::new()),
+            _ => None,
+        };
+        let text = "'stanza 'empty 'line";
+
+        let (output, log) = interpolator.interp_random_logged(text, &map).unwrap();
+        assert_eq!(
+            vec![
+                ("stanza", 0),
+                ("line", 0),
+                ("word", 0),
+                ("line", 1),
+                ("word", 1),
+                ("line", 2),
+                ("word", 2)
+            ],
+            log.choices
+                .iter()
+                .map(|choice| (choice.template.as_str(), choice.occurrence))
+                .collect::<Vec<_>>()
+        );
+
+        interpolator.seed(10);
+        assert_eq!(
+            (output.clone(), log.clone()),
+            interpolator.interp_replay(text, &map, &log).unwrap()
+        );
+
+        let mut edited = log.clone();
+        edited.choices[1].index = (edited.choices[1].index + 1) % 50;
+        edited
+            .choices
+            .retain(|choice| choice.template != "word" || choice.occurrence != 1);
+        let (replayed, replayed_log) = interpolator.interp_replay(text, &map, &edited).unwrap();
+        let lines: Vec<&str> = replayed.split(' ').collect();
+        let original: Vec<&str> = output.split(' ').collect();
+
+        assert_eq!(5, lines.len());
+        assert_ne!(original[0], lines[0]);
+        assert_eq!(original[0].split('.').next(), lines[0].split('.').next());
+        assert_eq!(original[3..], lines[3..]);
+        assert_eq!(edited.choices[1].index, replayed_log.choices[1].index);
+        assert_eq!(log.choices.len(), replayed_log.choices.len());
+    }
+
+    #[test]
+    fn strip_marker_writes_the_bare_name() {
+        let mut interpolator = TextInterpolator {
+            on_unresolved: UnresolvedAction::StripMarker,
+            ..Default::default()
+        };
+
+        let text = "('adjective) 'noun, 'missing's ''escaped 'missing'noun 'Owner#2 'nothing|'verb";
+        assert_eq!(
+            "(adjective) place, missing's 'escaped missingplace Owner run",
+            interpolator.interp(text, &map_template).unwrap()
+        );
+
+        let mut interpolator = defaults::with_braces();
+        interpolator.on_unresolved = UnresolvedAction::StripMarker;
+        assert_eq!(
+            "[first]-place!",
+            interpolator
+                .interp("[{first}]-{noun}!", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn adjacent_templates_in_suffix_are_substituted() {
+        let mut interpolator = TextInterpolator {
+            strict: true,
+            memoize: true,
+            ..Default::default()
+        };
+        let map = |template: &str| match template {
+            "greeting" => Some("Hello"),
+            "punctuation" => Some("!"),
+            "name" => Some("'greeting, 'noun"),
+            "noun" => Some("world"),
+            "a" => Some("'b'a"),
+            "b" => Some("b"),
+            _ => None,
+        };
+
+        assert_eq!(
+            "Hello! (Hello, world!) world's Hello''x",
+            interpolator
+                .interp(
+                    "'greeting'punctuation ('name'punctuation) 'noun's 'greeting''x",
+                    &map
+                )
+                .unwrap()
+        );
+        assert!(matches!(
+            interpolator.interp("'a", &map),
+            Err(InterpError::NestedTemplateLoop(err)) if err.chain == ["a", "a"] && err.depth == 2
+        ));
+
+        let segments = interpolator
+            .interp_segments("('name'punctuation)", &map)
+            .unwrap();
+        assert_eq!(
+            vec![
+                Segment::Literal("(".to_string()),
+                Segment::Substituted {
+                    template: "name".to_string(),
+                    value: "Hello, world".to_string(),
+                },
+                Segment::Substituted {
+                    template: "punctuation".to_string(),
+                    value: "!".to_string(),
+                },
+                Segment::Literal(")".to_string()),
+            ],
+            segments
+        );
+
+        let mut interpolator = defaults::with_braces();
+        assert_eq!(
+            "[Hello!]",
+            interpolator
+                .interp("[{greeting}{punctuation}]", &map)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn memoize_reuses_resolved_substitutions() {
+        let calls = RefCell::new(Vec::new());
+        let map = |template: &str| {
+            if !template.is_empty() {
+                calls.borrow_mut().push(template.to_string());
+            }
+            map_template(template)
+        };
+
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            ..Default::default()
+        };
+
+        let memoized = interpolator.interp("'paragraph 'sentence 'missing|x 'missing|y", &map);
+        assert_eq!(
+            vec![
+                "paragraph",
+                "sentence",
+                "verb",
+                "noun",
+                "missing",
+                "missing"
+            ],
+            *calls.borrow()
+        );
+
+        interpolator.memoize = false;
+        assert_eq!(
+            interpolator
+                .interp("'paragraph 'sentence 'missing|x 'missing|y", &map)
+                .unwrap(),
+            memoized.unwrap()
+        );
+    }
+
+    #[test]
+    fn memoize_is_ignored_for_random_candidates() {
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            ..Default::default()
+        };
+        interpolator.seed(3);
+
+        let interpolated_text = interpolator
+            .interp_random(&"'verb ".repeat(50), &map_candidates)
+            .unwrap();
+        let words: HashSet<&str> = interpolated_text.split_whitespace().collect();
+
+        assert!(words.len() > 1);
+    }
+
+    #[test]
+    fn find_templates_in_text() {
+        let interpolator = TextInterpolator::default();
+
+        let templates =
+            interpolator.find_templates("My Story:'paragraph... 'noun 'verb'ing no 'noun's");
+
+        assert_eq!(vec!["paragraph", "noun", "verb"], templates);
+    }
+
+    #[test]
+    fn interpolate_escaped_marker() {
+        let mut interpolator = TextInterpolator::default();
+
+        let interpolated_text = interpolator.interp("''tis the 'noun, ''noun", &map_template);
+
+        assert_eq!("'tis the place, 'noun", &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn interpolate_escaped_marker_in_substitution() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "quote" => Some("''tis".to_string()),
+            "nested" => Some("''tis the 'noun".to_string()),
+            _ => map_template(template),
+        };
+
+        assert_eq!("'tis", &interpolator.interp("'quote", &map).unwrap());
+        assert_eq!(
+            "'tis the place",
+            &interpolator.interp("'nested", &map).unwrap()
+        );
+        assert_eq!("'tis", interpolator.interp_cow("''tis", &map).unwrap());
+    }
+
+    #[test]
+    fn interpolate_templated_text() {
+        let mut interpolator = TextInterpolator::default();
+
+        let text: String = String::from("A 'adj 'noun will always 'verb in the morning.");
+        let interpolated_text = interpolator.interp(&text, &map_template);
+
+        dbg!(&interpolated_text);
+
+        assert!(!interpolator.contains_template(&interpolated_text.unwrap()));
+    }
+
+    #[test]
+    fn interpolate_templated_text_2() {
+        let mut interpolator = TextInterpolator::default();
+
+        let text: String = String::from("I'm 'verb'ing on some 'adj 'noun's right now.");
+        let interpolated_text = interpolator.interp(&text, &map_template);
+
+        dbg!(&interpolated_text);
+
+        assert!(!interpolator.contains_template(&interpolated_text.unwrap()));
+    }
 
-use core::fmt;
-use std::collections::HashSet;
+    #[test]
+    fn interpolate_keeps_full_suffix() {
+        let mut interpolator = TextInterpolator::default();
 
-#[derive(Debug, Clone)]
-pub struct NestedTemplateLoopError;
+        let interpolated_text =
+            interpolator.interp("'noun.. 'noun!? 'noun... 'noun's", &map_template);
 
-impl fmt::Display for NestedTemplateLoopError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "detected infinitely looping nested templates")
+        assert_eq!(
+            "place.. place!? place... place's",
+            &interpolated_text.unwrap()
+        );
     }
-}
 
-#[derive(Debug)]
-pub struct TemplateSplit<'a> {
-    pub prefix: &'a str,
-    pub template: &'a str,
-    pub suffix: &'a str,
-}
+    #[test]
+    fn interpolate_with_borrowed_substitutions() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "sentence" => Some(Cow::Borrowed("A 'adj 'noun.")),
+            "adj" => Some(Cow::Owned("funny".to_string())),
+            "noun" => Some(Cow::Borrowed("thing")),
+            _ => None,
+        };
 
-pub type IsTemplateFn = fn(&str) -> bool;
-pub type ExtractTemplateFn = fn(&str) -> TemplateSplit;
+        let interpolated_text = interpolator.interp("'sentence", &map);
 
-pub struct TextInterpolator {
-    pub is_template: IsTemplateFn,
-    pub extract_template: ExtractTemplateFn,
-    template_set: HashSet<String>,
-}
+        assert_eq!("A funny thing.", &interpolated_text.unwrap());
+    }
 
-impl Default for TextInterpolator {
-    /// Creates a TextInterpolator with a default is_template function and default extract_template
-    /// function
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use text_interpolator::TextInterpolator;
-    ///
-    /// let text_interpolator = TextInterpolator::default();
-    /// ```
-    fn default() -> Self {
-        TextInterpolator {
-            is_template: defaults::is_template,
-            extract_template: defaults::extract_template,
-            template_set: HashSet::new(),
-        }
+    #[test]
+    #[cfg(feature = "std")]
+    fn interpolate_with_hash_map() {
+        let mut interpolator = TextInterpolator::default();
+        let map: HashMap<String, String> = [("sentence", "A 'adj 'noun."), ("adj", "funny")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let interpolated_text = interpolator.interp_map("'sentence", &map);
+
+        assert_eq!("A funny 'noun.", &interpolated_text.unwrap());
     }
-}
 
-impl TextInterpolator {
-    pub fn new(is_template: IsTemplateFn, extract_template: ExtractTemplateFn) -> Self {
-        TextInterpolator {
-            is_template,
-            extract_template,
-            template_set: HashSet::new(),
-        }
+    fn map_candidates(template: &str) -> Option<Vec<String>> {
+        let candidates: &[&str] = match template {
+            "verb" => &["run", "fall", "fly", "swim"],
+            "noun" => &["person", "place", "thing"],
+            "sentence" => &["A 'noun should never 'verb.", "Never 'verb near a 'noun."],
+            "none" => &[],
+            _ => return None,
+        };
+
+        Some(candidates.iter().map(|c| c.to_string()).collect())
     }
 
-    pub fn interp(
-        &mut self,
-        text: &str,
-        map: &impl Fn(&str) -> Option<String>,
-    ) -> Result<String, NestedTemplateLoopError> {
-        // String will be at least as long as input
-        let mut output = String::with_capacity(text.len());
+    #[test]
+    fn interpolate_random_candidates_reproducible() {
+        let mut interpolator = TextInterpolator::default();
 
-        for item in text.split_whitespace() {
-            let template_split = (self.extract_template)(item);
+        let outputs: Vec<Vec<String>> = (0..2)
+            .map(|_| {
+                interpolator.seed(1234);
+                (0..10)
+                    .map(|_| {
+                        interpolator
+                            .interp_random("'sentence 'none", &map_candidates)
+                            .unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
 
-            match map(template_split.template) {
-                Some(substitute) => {
-                    if !self
-                        .template_set
-                        .insert(template_split.template.to_string())
-                    {
-                        self.template_set.clear();
-                        return Err(NestedTemplateLoopError);
-                    }
+        assert_eq!(outputs[0], outputs[1]);
+        assert!(outputs[0].iter().all(|output| output.ends_with(" 'none")));
+        assert!(outputs[0]
+            .iter()
+            .all(|output| !interpolator.contains_template(output.trim_end_matches(" 'none"))));
+    }
 
-                    let mut substitution = substitute;
+    #[test]
+    fn interpolate_random_with_custom_rng() {
+        let mut interpolator = TextInterpolator::default();
+        let mut counter = 0;
+        interpolator.rng = Box::new(move || {
+            counter += 1;
+            counter
+        });
 
-                    if self.contains_template(&substitution) {
-                        substitution = self.interp(&substitution, map)?;
-                    }
+        let interpolated_text = interpolator.interp_random("'verb 'verb 'verb", &map_candidates);
 
-                    self.template_set.remove(template_split.template);
+        assert_eq!("fall fly swim", &interpolated_text.unwrap());
+    }
 
-                    output.push_str(template_split.prefix);
-                    output.push_str(&substitution);
-                    output.push_str(template_split.suffix);
-                    output.push(' ');
-                }
-                None => {
-                    output.push_str(item);
-                    output.push(' ');
-                }
-            }
+    #[test]
+    fn self_referential_grammars_terminate_without_loop_detection() {
+        let mut interpolator = TextInterpolator {
+            detect_loops: false,
+            ..Default::default()
+        };
+        interpolator.seed(8);
+        let map = |template: &str| match template {
+            "list" => Some(vec!["'item, 'list".to_string(), "'item".to_string()]),
+            "item" => Some(vec!["a".to_string(), "b".to_string()]),
+            _ => None,
+        };
+
+        let mut lengths = BTreeSet::new();
+        for _ in 0..50 {
+            let output = interpolator.interp_random("'list.", &map).unwrap();
+            assert!(output
+                .trim_end_matches('.')
+                .split(", ")
+                .all(|item| item == "a" || item == "b"));
+            lengths.insert(output.len());
         }
+        assert!(lengths.len() > 1);
 
-        // Remove trailing space
-        output.pop();
+        assert!(matches!(
+            interpolator.interp("'infinite", &map_template),
+            Err(InterpError::MaxDepthExceeded { depth: 65 })
+        ));
 
-        Ok(output)
+        interpolator.detect_loops = true;
+        interpolator.rng = Box::new(|| 0);
+        assert!(matches!(
+            interpolator.interp_random("'list", &map),
+            Err(InterpError::NestedTemplateLoop(err)) if err.chain == ["list", "list"]
+        ));
     }
 
-    pub fn contains_template(&self, text: &str) -> bool {
-        for item in text.split_whitespace() {
-            if (self.is_template)(item) {
-                return true;
-            }
+    #[test]
+    fn interpolate_weighted_skips_zero_weights() {
+        let mut interpolator = TextInterpolator::default();
+        interpolator.seed(99);
+        let map = |template: &str| match template {
+            "article" => Some(vec![
+                (0, "an".to_string()),
+                (7, "the".to_string()),
+                (3, "a".to_string()),
+            ]),
+            "empty" => Some(vec![]),
+            _ => None,
+        };
+
+        for _ in 0..100 {
+            let interpolated_text = interpolator
+                .interp_weighted("'article 'empty", &map)
+                .unwrap();
+            assert!(["the 'empty", "a 'empty"].contains(&interpolated_text.as_str()));
         }
-        false
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn interpolate_cycle_takes_candidates_in_turn() {
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            ..Default::default()
+        };
+        let mut lookups = 0;
+        let mut map = |template: &str| {
+            lookups += 1;
+            match template {
+                "color" => Some(
+                    vec!["red", "green", "blue"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                ),
+                "empty" => Some(vec![]),
+                _ => None,
+            }
+        };
 
-    fn map_template(template: &str) -> Option<String> {
-        match template {
-            "verb" => Some(["run", "fall", "fly", "swim"][0].to_string()),
-            "noun" => Some(["person", "place", "thing"][1].to_string()),
-            "adj" => Some(["funny", "interesting", "aggrivating"][2].to_string()),
-            "sentence" => Some(
-                [
-                    "A 'adj 'noun should never 'verb..",
-                    "I've never seen someone 'verb with a 'noun before.",
-                    "You are too 'adj to be 'adj..",
-                ][1]
-                .to_string(),
-            ),
-            "paragraph" => Some(["'sentence 'sentence 'sentence"][0].to_string()),
-            "infinite" => Some("'infinite".to_string()),
-            "nonexistantnest" => Some("'nothing".to_string()),
-            _ => None,
+        for _ in 0..2 {
+            assert_eq!(
+                "red green blue Red 'empty",
+                interpolator
+                    .interp_cycle("'color 'color 'color 'Color 'empty", &mut map)
+                    .unwrap()
+            );
         }
+        assert_eq!(12, lookups);
+        assert!(interpolator.memoize);
     }
 
     #[test]
-    fn interpolate_non_templated_text() {
+    fn interpolate_segments_keep_provenance() {
         let mut interpolator = TextInterpolator::default();
 
-        let text: String = String::from(
-            "This is an example of a basic input with no templates to be substituted.",
-        );
-        let interpolated_text = interpolator.interp(&text, &map_template);
-
-        dbg!(&interpolated_text);
+        let text = "My Story:'sentence.. 'missing 'noun";
+        let segments = interpolator.interp_segments(text, &map_template).unwrap();
 
-        assert_eq!(&text, &interpolated_text.unwrap());
+        assert_eq!(
+            vec![
+                Segment::Literal("My Story:".to_string()),
+                Segment::Substituted {
+                    template: "sentence".to_string(),
+                    value: "I've never seen someone run with a place before.".to_string(),
+                },
+                Segment::Literal(".. 'missing ".to_string()),
+                Segment::Substituted {
+                    template: "noun".to_string(),
+                    value: "place".to_string(),
+                },
+            ],
+            segments
+        );
     }
 
     #[test]
-    fn interpolate_templated_text() {
+    fn interpolate_default_values() {
         let mut interpolator = TextInterpolator::default();
 
-        let text: String = String::from("A 'adj 'noun will always 'verb in the morning.");
-        let interpolated_text = interpolator.interp(&text, &map_template);
-
-        dbg!(&interpolated_text);
+        let interpolated_text = interpolator.interp(
+            "'noun|thing 'missing|thing. 'missing|'adj 'missing|'other|last!",
+            &map_template,
+        );
 
-        assert!(!interpolator.contains_template(&interpolated_text.unwrap()));
+        assert_eq!(
+            "place thing. aggrivating last!",
+            &interpolated_text.unwrap()
+        );
     }
 
     #[test]
-    fn interpolate_templated_text_2() {
+    fn interpolate_default_values_detect_loops() {
         let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "loop" => Some("'missing|'loop".to_string()),
+            _ => None,
+        };
 
-        let text: String = String::from("I'm 'verb'ing on some 'adj 'noun's right now.");
-        let interpolated_text = interpolator.interp(&text, &map_template);
-
-        dbg!(&interpolated_text);
-
-        assert!(!interpolator.contains_template(&interpolated_text.unwrap()));
+        assert!(interpolator.interp("'loop", &map).is_err());
     }
 
     #[test]
@@ -241,6 +5284,457 @@ mod tests {
         dbg!(interp_text.unwrap());
     }
 
+    #[test]
+    fn interpolate_with_capturing_closures() {
+        let markers: HashSet<char> = ['$', '@'].into_iter().collect();
+        let extract_markers = markers.clone();
+
+        let mut interpolator = TextInterpolator::new(
+            move |text| text.chars().next().is_some_and(|c| markers.contains(&c)),
+            move |text| match text.split_once(|c| extract_markers.contains(&c)) {
+                Some((prefix, template)) => TemplateSplit {
+                    prefix,
+                    template,
+                    ..Default::default()
+                },
+                None => TemplateSplit::default(),
+            },
+        );
+
+        let interpolated_text = interpolator.interp("A $adj @noun", &map_template);
+
+        assert_eq!("A aggrivating place", &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn max_depth_exceeded() {
+        fn map_chain(template: &str) -> Option<String> {
+            let n: usize = template.strip_prefix("chain")?.parse().ok()?;
+            match n {
+                5000 => Some("end".to_string()),
+                _ => Some(format!("'chain{}", n + 1)),
+            }
+        }
+
+        let mut interpolator = TextInterpolator::default();
+
+        let interpolated_text = interpolator.interp("'chain0", &map_chain);
+
+        assert!(matches!(
+            interpolated_text,
+            Err(InterpError::MaxDepthExceeded { depth: 65 })
+        ));
+    }
+
+    #[test]
+    fn deep_nesting_does_not_overflow_stack() {
+        fn map_chain(template: &str) -> Option<String> {
+            let n: usize = template.strip_prefix("chain")?.parse().ok()?;
+            match n {
+                100_000 => Some("end".to_string()),
+                _ => Some(format!("< 'chain{} >", n + 1)),
+            }
+        }
+
+        let mut interpolator = TextInterpolator {
+            max_depth: usize::MAX,
+            ..Default::default()
+        };
+
+        let interpolated_text = interpolator.interp("'chain0", &map_chain).unwrap();
+
+        assert_eq!(100_000 * 4 + 3, interpolated_text.len());
+        assert!(interpolated_text.starts_with("< < < "));
+        assert!(interpolated_text.ends_with(" > > >"));
+        assert_eq!(Some(100_000 * 2), interpolated_text.find("end"));
+    }
+
+    #[test]
+    fn max_depth_allows_nesting_within_limit() {
+        let mut interpolator = TextInterpolator {
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        let interpolated_text = interpolator.interp("'sentence", &map_template);
+        assert!(!interpolator.contains_template(&interpolated_text.unwrap()));
+
+        let interpolated_text = interpolator.interp("'paragraph", &map_template);
+        assert!(matches!(
+            interpolated_text,
+            Err(InterpError::MaxDepthExceeded { depth: 3 })
+        ));
+    }
+
+    #[test]
+    fn max_depth_zero_disables_substitution() {
+        let mut interpolator = TextInterpolator {
+            max_depth: 0,
+            ..Default::default()
+        };
+
+        let interpolated_text = interpolator.interp("A 'adj 'noun", &map_template);
+
+        assert_eq!("A 'adj 'noun", &interpolated_text.unwrap());
+    }
+
+    #[test]
+    fn interpolator_reusable_after_error() {
+        fn map_loop(template: &str) -> Option<String> {
+            match template {
+                "outer" => Some("'verb 'inner".to_string()),
+                "inner" => Some("'outer".to_string()),
+                _ => map_template(template),
+            }
+        }
+
+        let mut interpolator = TextInterpolator::default();
+
+        assert!(interpolator.interp("'outer", &map_loop).is_err());
+        assert_eq!("run", interpolator.interp("'verb", &map_loop).unwrap());
+
+        interpolator.max_depth = 1;
+        assert!(interpolator.interp("'sentence", &map_template).is_err());
+
+        interpolator.max_depth = defaults::MAX_DEPTH;
+        let interpolated_text = interpolator.interp("'sentence", &map_template);
+        assert!(!interpolator.contains_template(&interpolated_text.unwrap()));
+    }
+
+    #[test]
+    fn panicking_map_leaves_no_state_behind() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "bad" => panic!("map failed"),
+            "outer" => Some("'noun 'bad".to_string()),
+            _ => map_template(template),
+        };
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            interpolator.interp_collecting("'outer", &map)
+        }));
+        assert!(result.is_err());
+
+        assert_eq!(
+            "place run",
+            interpolator
+                .interp("'outer", &|template| match template {
+                    "outer" => Some("'noun 'verb".to_string()),
+                    _ => map_template(template),
+                })
+                .unwrap()
+        );
+        assert_eq!(
+            vec!["missing"],
+            interpolator
+                .interp_collecting("'missing", &map_template)
+                .unwrap()
+                .1
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn reset_leaves_the_interpolator_as_it_was() {
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            sticky: true,
+            ..Default::default()
+        };
+        interpolator.add_alias("thing", "noun");
+        interpolator.seed(3);
+        let mut reseeded = TextInterpolator::default();
+        reseeded.seed(3);
+        let digits = |template: &str| match template {
+            "digit" => Some((0..10).map(|digit| digit.to_string()).collect()),
+            _ => None,
+        };
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            interpolator.interp("'noun 'bad", &|template| match template {
+                "bad" => panic!("map failed"),
+                _ => map_template(template),
+            })
+        }));
+        assert!(result.is_err());
+
+        interpolator.reset();
+
+        assert!(interpolator.memoize);
+        assert!(interpolator.sticky);
+        assert_eq!(
+            "place",
+            interpolator.interp("'thing", &map_template).unwrap()
+        );
+        assert_eq!(
+            reseeded.interp_random("'digit", &digits).unwrap(),
+            interpolator.interp_random("'digit", &digits).unwrap()
+        );
+    }
+
+    #[test]
+    fn panicking_map_leaves_settings_as_they_were() {
+        let mut interpolator = TextInterpolator {
+            memoize: true,
+            rng: Box::new(|| 1),
+            on_substitute: Some(Box::new(|_, _, _| {})),
+            ..Default::default()
+        };
+        let digits = |template: &str| match template {
+            "digit" => Some((0..10).map(|digit| digit.to_string()).collect()),
+            _ => None,
+        };
+
+        for result in [
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                interpolator.interp_random("'digit 'bad", &|template| match template {
+                    "bad" => panic!("map failed"),
+                    _ => digits(template),
+                })
+            })),
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                interpolator.interp_cycle("'bad", &mut |_| panic!("map failed"))
+            })),
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                interpolator
+                    .explain("'bad", &|_| -> Option<&str> { panic!("map failed") })
+                    .map(|tree| tree.output)
+            })),
+        ] {
+            assert!(result.is_err());
+            assert!(interpolator.memoize);
+            assert!(interpolator.on_substitute.is_some());
+        }
+
+        assert_eq!(
+            "1 1",
+            interpolator
+                .interp_random("'digit 'digit", &digits)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn branches_not_taken_are_never_expanded() {
+        let mut interpolator = TextInterpolator {
+            strict: true,
+            ..Default::default()
+        };
+        let looked_up = RefCell::new(Vec::new());
+        let map = |template: &str| {
+            looked_up.borrow_mut().push(template.to_string());
+            map_template(template)
+        };
+
+        assert_eq!(
+            "place place",
+            interpolator
+                .interp("'noun|'infinite 'noun|'nothing", &map)
+                .unwrap()
+        );
+        assert_eq!(vec!["noun", "noun"], *looked_up.borrow());
+
+        interpolator.seed(3);
+        let map = |template: &str| match template {
+            "pick" => Some(vec!["fine".to_string(), "'infinite".to_string()]),
+            "infinite" => Some(vec!["'infinite".to_string()]),
+            _ => None,
+        };
+        let mut picked_fine = false;
+        for _ in 0..16 {
+            match interpolator.interp_random("'pick", &map) {
+                Ok(output) => {
+                    assert_eq!("fine", output);
+                    picked_fine = true;
+                }
+                Err(err) => assert!(matches!(err, InterpError::NestedTemplateLoop(_))),
+            }
+        }
+        assert!(picked_fine);
+    }
+
+    #[test]
+    fn aliases_resolve_as_their_target() {
+        let mut interpolator = TextInterpolator::default();
+        interpolator.add_alias("colour", "color");
+        interpolator.add_alias("hue", "colour");
+        interpolator.add_alias("shade", "shade");
+        let map = |template: &str| match template {
+            "color" => Some("'shade"),
+            "sentence" => Some("'hue, 'Colour 'color"),
+            _ => None,
+        };
+
+        assert!(matches!(
+            interpolator.interp("'hue", &map),
+            Err(InterpError::NestedTemplateLoop(err))
+                if err.chain == ["shade", "shade"] && err.depth == 2
+        ));
+        assert_eq!(
+            Err(vec![ValidationIssue::NestedTemplateLoop(
+                NestedTemplateLoopError {
+                    chain: vec!["shade".to_string(), "shade".to_string()],
+                    depth: 3,
+                }
+            )]),
+            interpolator.validate("'sentence", &map)
+        );
+
+        interpolator.aliases.remove("shade");
+        let map = |template: &str| match template {
+            "color" => Some(vec!["red".to_string(), "blue".to_string()]),
+            _ => None,
+        };
+        interpolator.sticky = true;
+        for _ in 0..8 {
+            let output = interpolator
+                .interp_random("'hue 'Colour 'color", &map)
+                .unwrap();
+            let words: Vec<String> = output.split(' ').map(str::to_lowercase).collect();
+            assert!(words.iter().all(|word| *word == words[0]), "{output}");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn interp_subset_leaves_other_templates_for_later() {
+        let mut interpolator = TextInterpolator {
+            strict: true,
+            on_unresolved: UnresolvedAction::Remove,
+            ..Default::default()
+        };
+        let allowed = HashSet::from(["sentence".to_string(), "noun".to_string()]);
+        let text = "'sentence 'Noun 'adj'noun 'verb|default";
+
+        let first = interpolator
+            .interp_subset(text, &map_template, &allowed)
+            .unwrap();
+        assert_eq!(
+            "I've never seen someone 'verb with a place before. Place 'adj'noun 'verb|default",
+            first
+        );
+        assert_eq!(
+            interpolator.interp(text, &map_template).unwrap(),
+            interpolator.interp(&first, &map_template).unwrap()
+        );
+
+        assert!(matches!(
+            interpolator.interp_subset(
+                "'nothing",
+                &map_template,
+                &HashSet::from(["nothing".to_string()])
+            ),
+            Err(InterpError::UndefinedTemplate { .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_templates_are_rejected() {
+        let mut interpolator = TextInterpolator {
+            reject_unknown: true,
+            ..Default::default()
+        };
+        interpolator.register_templates(["greeting", "name", "unset"].map(String::from));
+        let map = |template: &str| match template {
+            "greeting" => Some("Hello 'name'suffix"),
+            "name" => Some("'nickname"),
+            "nickname" => Some("Ada"),
+            "suffix" => Some("!"),
+            _ => None,
+        };
+
+        assert_eq!(
+            "Hello 'name's 'unset 'escaped",
+            interpolator
+                .interp("Hello 'name's 'unset ''escaped", &|_| None::<&str>)
+                .unwrap()
+        );
+        assert!(matches!(
+            interpolator.interp("Say 'Greeting", &map),
+            Err(InterpError::UnknownTemplate { template, span })
+                if template == "nickname" && span == (4..13)
+        ));
+        assert_eq!(
+            Err(vec![
+                ValidationIssue::UnknownTemplate("nickname".to_string()),
+                ValidationIssue::UnknownTemplate("other".to_string()),
+            ]),
+            interpolator.validate("'greeting 'other", &map)
+        );
+
+        interpolator.reject_unknown = false;
+        assert_eq!(
+            "Hello Ada!",
+            interpolator.interp("'greeting", &map).unwrap()
+        );
+    }
+
+    #[test]
+    fn undefined_template_errors_locate_the_input_word() {
+        let mut interpolator = TextInterpolator {
+            strict: true,
+            ..Default::default()
+        };
+        let text = "A 'noun,\nthen ('missing)";
+
+        let Err(err) = interpolator.interp(text, &map_template) else {
+            panic!("expected an undefined template error");
+        };
+        let InterpError::UndefinedTemplate { template, span } = &err else {
+            panic!("expected an undefined template error");
+        };
+        assert_eq!("missing", template);
+        assert_eq!("('missing)", &text[span.clone()]);
+        assert_eq!(
+            "template 'missing' has no substitution at bytes 14..24",
+            err.to_string()
+        );
+
+        for result in [
+            interpolator.interp_lines(text, &map_template),
+            interpolator
+                .interp_cow("A 'noun,\nthen ('missing)", &map_template)
+                .map(Cow::into_owned),
+        ] {
+            assert!(matches!(
+                result,
+                Err(InterpError::UndefinedTemplate { span, .. }) if span == (14..24)
+            ));
+        }
+        assert!(matches!(
+            interpolator.interp("'noun#dunno", &map_template),
+            Err(InterpError::UndefinedTemplate { span, .. }) if span == (0..11)
+        ));
+    }
+
+    #[test]
+    fn strict_mode_rejects_undefined_templates() {
+        let mut interpolator = TextInterpolator {
+            strict: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "I'm a place thing",
+            interpolator
+                .interp("I'm a 'noun 'missing|thing", &map_template)
+                .unwrap()
+        );
+        assert!(matches!(
+            interpolator.interp("'noun 'nonexistantnest", &map_template),
+            Err(InterpError::UndefinedTemplate { template, span })
+                if template == "nothing" && span == (6..22)
+        ));
+
+        interpolator.strict = false;
+        assert_eq!(
+            "place 'nothing",
+            interpolator
+                .interp("'noun 'nonexistantnest", &map_template)
+                .unwrap()
+        );
+    }
+
     #[test]
     fn infinite_self_recursion() {
         let mut interpolator = TextInterpolator::default();
@@ -251,4 +5745,40 @@ mod tests {
 
         assert!(&interpolated_text.is_err());
     }
+
+    #[test]
+    fn errors_box_into_dyn_error() {
+        fn render() -> Result<String, Box<dyn std::error::Error>> {
+            let mut interpolator = TextInterpolator::default();
+            Ok(interpolator.interp("'infinite", &map_template)?)
+        }
+
+        let err = render().unwrap_err();
+
+        assert!(err.source().is_some());
+        assert!(err.to_string().contains("infinite -> infinite"));
+    }
+
+    #[test]
+    fn loop_error_reports_cycle_chain() {
+        let mut interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "start" => Some("'a"),
+            "a" => Some("'b"),
+            "b" => Some("x 'c"),
+            "c" => Some("'a"),
+            _ => None,
+        };
+
+        let Err(InterpError::NestedTemplateLoop(err)) = interpolator.interp("'start", &map) else {
+            panic!("expected a loop error");
+        };
+
+        assert_eq!(vec!["a", "b", "c", "a"], err.chain);
+        assert_eq!(5, err.depth);
+        assert_eq!(
+            "detected infinitely looping nested templates at depth 5: a -> b -> c -> a",
+            err.to_string()
+        );
+    }
 }
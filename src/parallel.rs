@@ -0,0 +1,126 @@
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::{InterpError, Substitution, TextInterpolator};
+
+impl TextInterpolator {
+    /// Interpolates many independent texts across all available cores, returning one result
+    /// per text in the same order.
+    ///
+    /// Each text is interpolated the same way as [`TextInterpolator::interp_ref`], so no
+    /// mutable state is shared between threads. The map is called from several threads at
+    /// once and therefore has to be `Sync`. A failing text doesn't stop the others, while a
+    /// panic in map is resumed on the calling thread with its original payload.
+    ///
+    /// Only available with the `parallel` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world"),
+    ///     "loop" => Some("'loop"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let results = text_interpolator.interp_par(&["Hello 'name", "'loop"], &map);
+    ///
+    /// assert_eq!("Hello world", results[0].as_ref().unwrap());
+    /// assert!(results[1].is_err());
+    /// ```
+    pub fn interp_par<'m, S: Into<Substitution<'m>>>(
+        &self,
+        texts: &[&str],
+        map: &(impl Fn(&str) -> Option<S> + Sync),
+    ) -> Vec<Result<String, InterpError>> {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk_size = texts.len().div_ceil(threads).max(1);
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = texts
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|text| self.interp_ref(text, map))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_template(template: &str) -> Option<String> {
+        match template {
+            "sentence" => Some("A 'adj 'noun.".to_string()),
+            "adj" => Some("funny".to_string()),
+            "noun" => Some("place".to_string()),
+            "loop" => Some("'loop".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interp_par_keeps_order() {
+        let text_interpolator = TextInterpolator::default();
+        let texts: Vec<String> = (0..1000)
+            .map(|index| match index % 3 {
+                0 => format!("{index} 'sentence"),
+                1 => format!("{index} 'loop"),
+                _ => format!("{index} 'missing"),
+            })
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+
+        let results = text_interpolator.interp_par(&texts, &map_template);
+
+        assert_eq!(texts.len(), results.len());
+        for (text, result) in texts.iter().zip(results) {
+            match result {
+                Ok(output) => {
+                    assert_eq!(
+                        text_interpolator.interp_ref(text, &map_template).unwrap(),
+                        output
+                    )
+                }
+                Err(err) => assert!(matches!(err, InterpError::NestedTemplateLoop(_))),
+            }
+        }
+
+        assert!(text_interpolator.interp_par(&[], &map_template).is_empty());
+    }
+
+    #[test]
+    fn interp_par_resumes_panics_in_map() {
+        let text_interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "boom" => panic!("boom"),
+            _ => map_template(template),
+        };
+
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            text_interpolator.interp_par(&["'sentence", "'boom"], &map)
+        }))
+        .unwrap_err();
+
+        assert_eq!(Some(&"boom"), payload.downcast_ref::<&str>());
+    }
+}
@@ -0,0 +1,171 @@
+//! English pluralization of substitutions.
+//!
+//! A template written as `'noun#count` is pluralized when the count is not 1, where count is
+//! either a number or the name of a template whose substitution is a number. Regular nouns
+//! follow the usual suffix rules, and irregular ones can be registered on a [`Pluralizer`].
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+/// Irregular plurals known to every [`Pluralizer`].
+const IRREGULAR: &[(&str, &str)] = &[
+    ("child", "children"),
+    ("foot", "feet"),
+    ("goose", "geese"),
+    ("man", "men"),
+    ("mouse", "mice"),
+    ("person", "people"),
+    ("tooth", "teeth"),
+    ("woman", "women"),
+];
+
+/// Turns singular nouns into their plural, consulting a table of irregular forms first.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::plural::Pluralizer;
+///
+/// let mut pluralizer = Pluralizer::default();
+/// pluralizer.irregular("octopus", "octopodes");
+///
+/// assert_eq!("big cats", pluralizer.pluralize("big cat"));
+/// assert_eq!("Mice", pluralizer.pluralize("Mouse"));
+/// assert_eq!("octopodes", pluralizer.pluralize("octopus"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pluralizer {
+    irregular: BTreeMap<String, String>,
+}
+
+impl Default for Pluralizer {
+    fn default() -> Self {
+        Pluralizer {
+            irregular: IRREGULAR
+                .iter()
+                .map(|&(singular, plural)| (singular.to_string(), plural.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Pluralizer {
+    /// Registers the plural of a noun that doesn't follow the regular rules, replacing any
+    /// plural registered for it before.
+    pub fn irregular(&mut self, singular: impl Into<String>, plural: impl Into<String>) {
+        self.irregular
+            .insert(singular.into().to_lowercase(), plural.into());
+    }
+
+    /// Pluralizes the last word of text, keeping its capitalization.
+    pub fn pluralize(&self, text: &str) -> String {
+        let start = text
+            .char_indices()
+            .rev()
+            .find(|(_, ch)| ch.is_whitespace())
+            .map_or(0, |(index, ch)| index + ch.len_utf8());
+        let (before, word) = text.split_at(start);
+
+        if word.is_empty() {
+            return text.to_string();
+        }
+
+        let upper = word.chars().filter(|ch| ch.is_alphabetic()).count() > 1
+            && !word.chars().any(char::is_lowercase);
+        let lower = word.to_lowercase();
+
+        let plural = match self.irregular.get(&lower) {
+            Some(plural) if upper => plural.to_uppercase(),
+            Some(plural) => match word.chars().next() {
+                Some(first) if first.is_uppercase() => {
+                    let mut chars = plural.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                }
+                _ => plural.clone(),
+            },
+            None => {
+                let (stem, suffix) = regular_suffix(word, &lower);
+                if upper {
+                    format!("{stem}{}", suffix.to_uppercase())
+                } else {
+                    format!("{stem}{suffix}")
+                }
+            }
+        };
+
+        format!("{before}{plural}")
+    }
+}
+
+/// Splits a regular noun into the part that is kept and the suffix that makes it plural.
+fn regular_suffix<'a>(word: &'a str, lower: &str) -> (&'a str, &'static str) {
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|ending| lower.ends_with(ending))
+    {
+        return (word, "es");
+    }
+
+    let mut chars = lower.chars().rev();
+    if let (Some('y'), Some(before)) = (chars.next(), chars.next()) {
+        if !"aeiou".contains(before) {
+            return (&word[..word.len() - 1], "ies");
+        }
+    }
+
+    (word, "s")
+}
+
+/// Pluralizes the last word of text with the built-in rules, see [`Pluralizer::pluralize`].
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::plural::pluralize;
+///
+/// assert_eq!("cats", pluralize("cat"));
+/// assert_eq!("boxes", pluralize("box"));
+/// assert_eq!("cities", pluralize("city"));
+/// assert_eq!("children", pluralize("child"));
+/// ```
+pub fn pluralize(text: &str) -> String {
+    Pluralizer::default().pluralize(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regular_plurals() {
+        let pluralizer = Pluralizer::default();
+
+        for (singular, plural) in [
+            ("cat", "cats"),
+            ("bus", "buses"),
+            ("church", "churches"),
+            ("dish", "dishes"),
+            ("day", "days"),
+            ("party", "parties"),
+            ("CAT", "CATS"),
+            ("old  city", "old  cities"),
+        ] {
+            assert_eq!(plural, pluralizer.pluralize(singular));
+        }
+    }
+
+    #[test]
+    fn irregular_plurals_keep_case() {
+        let mut pluralizer = Pluralizer::default();
+        pluralizer.irregular("Cactus", "cacti");
+
+        assert_eq!("PEOPLE", pluralizer.pluralize("PERSON"));
+        assert_eq!("Feet", pluralizer.pluralize("Foot"));
+        assert_eq!("green cacti", pluralizer.pluralize("green cactus"));
+        assert_eq!("", pluralizer.pluralize(""));
+    }
+}
@@ -0,0 +1,195 @@
+//! Random selection among substitution candidates.
+//!
+//! The interpolator draws random numbers from a pluggable `FnMut() -> usize` so any random number
+//! generator can be injected. [`Rng`] is a small seedable generator used by default, which keeps
+//! output reproducible whenever a seed is set.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hasher};
+
+/// A small, fast, seedable pseudo-random number generator (SplitMix64).
+///
+/// It is not cryptographically secure, but it is more than random enough for picking
+/// substitutions, and the same seed always produces the same sequence.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::random::Rng;
+///
+/// let mut a = Rng::new(7);
+/// let mut b = Rng::new(7);
+///
+/// assert_eq!(a.next_u64(), b.next_u64());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Creates an Rng seeded from the same per-process randomness used by `HashMap`.
+    #[cfg(feature = "std")]
+    pub fn from_entropy() -> Self {
+        Rng::new(RandomState::new().build_hasher().finish())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_usize(&mut self) -> usize {
+        self.next_u64() as usize
+    }
+}
+
+/// Picks an index below len from a random number.
+///
+/// Returns None if len is 0.
+pub fn choose_index(rng: &mut dyn FnMut() -> usize, len: usize) -> Option<usize> {
+    match len {
+        0 => None,
+        _ => Some(rng() % len),
+    }
+}
+
+/// Picks an index into weights with probability proportional to each weight, using a
+/// cumulative-weight draw.
+///
+/// Entries with a weight of 0 are never chosen, unless every weight is 0, in which case all
+/// entries are equally likely. Returns None if weights is empty.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::random::{choose_weighted, Rng};
+///
+/// let mut rng = Rng::new(3);
+/// let mut next = || rng.next_usize();
+///
+/// assert_eq!(Some(1), choose_weighted(&mut next, &[0, 5, 0]));
+/// assert_eq!(None, choose_weighted(&mut next, &[]));
+/// ```
+pub fn choose_weighted(rng: &mut dyn FnMut() -> usize, weights: &[u32]) -> Option<usize> {
+    let total: u64 = weights.iter().map(|&weight| u64::from(weight)).sum();
+
+    if total == 0 {
+        return choose_index(rng, weights.len());
+    }
+
+    let mut draw = rng() as u64 % total;
+
+    weights.iter().position(|&weight| {
+        let weight = u64::from(weight);
+        if draw < weight {
+            true
+        } else {
+            draw -= weight;
+            false
+        }
+    })
+}
+
+/// The candidates chosen while interpolating text with random substitutions, as returned by
+/// [`TextInterpolator::interp_random_logged`], so the same output can be replayed later.
+///
+/// Choices can be edited or removed before replaying them with
+/// [`TextInterpolator::interp_replay`]: only the templates without a choice are drawn afresh,
+/// which regenerates parts of a text while keeping the rest.
+///
+/// [`TextInterpolator::interp_random_logged`]: crate::TextInterpolator::interp_random_logged
+/// [`TextInterpolator::interp_replay`]: crate::TextInterpolator::interp_replay
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChoiceLog {
+    /// Choices in the order they were made, nested ones included.
+    pub choices: Vec<Choice>,
+}
+
+/// A candidate chosen for one occurrence of a template, see [`ChoiceLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choice {
+    pub template: String,
+    /// How many times the template was drawn for before this one in the same call, starting
+    /// at 0.
+    pub occurrence: usize,
+    /// Index of the chosen candidate among the ones the map returned.
+    pub index: usize,
+}
+
+impl ChoiceLog {
+    /// Index of the candidate chosen for the given occurrence of a template, if it was logged.
+    pub fn index_of(&self, template: &str, occurrence: usize) -> Option<usize> {
+        self.choices
+            .iter()
+            .find(|choice| choice.template == template && choice.occurrence == occurrence)
+            .map(|choice| choice.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn choose_index_in_range() {
+        let mut rng = Rng::new(1);
+        let mut next = || rng.next_usize();
+
+        assert_eq!(None, choose_index(&mut next, 0));
+
+        for _ in 0..100 {
+            assert!(choose_index(&mut next, 3).unwrap() < 3);
+        }
+    }
+
+    #[test]
+    fn choose_weighted_honors_weights() {
+        let mut rng = Rng::new(9);
+        let mut next = || rng.next_usize();
+        let mut counts = [0; 3];
+
+        for _ in 0..10_000 {
+            counts[choose_weighted(&mut next, &[70, 0, 30]).unwrap()] += 1;
+        }
+
+        assert_eq!(0, counts[1]);
+        assert!((6500..7500).contains(&counts[0]));
+        assert!((2500..3500).contains(&counts[2]));
+    }
+
+    #[test]
+    fn choose_weighted_all_zero_is_uniform() {
+        let mut rng = Rng::new(9);
+        let mut next = || rng.next_usize();
+        let mut counts = [0; 2];
+
+        for _ in 0..1000 {
+            counts[choose_weighted(&mut next, &[0, 0]).unwrap()] += 1;
+        }
+
+        assert!(counts.iter().all(|&count| count > 0));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use crate::{InterpError, TextInterpolator};
+
+/// A [`TextInterpolator`] bundled with the dictionary it substitutes from, for the simple case
+/// of filling templates from a `HashMap` without passing a map to every call.
+///
+/// Both halves are public, so the interpolator can be configured and the dictionary updated
+/// between renders.
+///
+/// Only available with the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashMap;
+/// use text_interpolator::SimpleInterpolator;
+///
+/// let mut simple = SimpleInterpolator::from(HashMap::from([
+///     ("greeting".to_string(), "Hello 'name".to_string()),
+///     ("name".to_string(), "world".to_string()),
+/// ]));
+///
+/// assert_eq!("Hello world!", simple.render("'greeting!").unwrap());
+///
+/// simple.map.insert("name".to_string(), "Ada".to_string());
+/// assert_eq!("Hello Ada!", simple.render("'greeting!").unwrap());
+/// ```
+pub struct SimpleInterpolator {
+    pub interpolator: TextInterpolator,
+    pub map: HashMap<String, String>,
+}
+
+impl From<HashMap<String, String>> for SimpleInterpolator {
+    /// Uses a [`TextInterpolator::default`] to substitute from map.
+    fn from(map: HashMap<String, String>) -> Self {
+        SimpleInterpolator {
+            interpolator: TextInterpolator::default(),
+            map,
+        }
+    }
+}
+
+impl SimpleInterpolator {
+    /// Interpolates text with substitutions from the dictionary, see
+    /// [`TextInterpolator::interp`].
+    pub fn render(&mut self, text: &str) -> Result<String, InterpError> {
+        let map = &self.map;
+
+        self.interpolator
+            .interp(text, &|template| map.get(template))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_uses_the_interpolator_configuration() {
+        let mut simple = SimpleInterpolator::from(HashMap::from([
+            ("noun".to_string(), "place".to_string()),
+            ("loop".to_string(), "'loop".to_string()),
+        ]));
+
+        assert_eq!(
+            "A place, 'missing",
+            simple.render("A 'noun, 'missing").unwrap()
+        );
+        assert!(matches!(
+            simple.render("'loop"),
+            Err(InterpError::NestedTemplateLoop(_))
+        ));
+
+        simple.interpolator.strict = true;
+        assert!(matches!(
+            simple.render("'missing"),
+            Err(InterpError::UndefinedTemplate { .. })
+        ));
+    }
+}
@@ -0,0 +1,260 @@
+use std::io::{self, Read, Write};
+
+use crate::{ignore_args, CallState, InterpError, Substitution, TextInterpolator};
+
+/// Number of bytes requested from the reader at a time.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+impl TextInterpolator {
+    /// Interpolates everything read from reader, writing the output to writer incrementally
+    /// instead of loading the whole input into memory.
+    ///
+    /// Input is buffered only until the last word of each chunk without a template, so a word
+    /// split across two reads is held back until the rest of it arrives, and so is a
+    /// conditional until its closing parenthesis does, however many words its body has. Words
+    /// with a template are held back along with the separators around them, since a template
+    /// that comes out empty decides which of them are kept. The output is identical to calling
+    /// [`TextInterpolator::interp`] on the whole input.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the input is not valid UTF-8 or the
+    /// interpolation itself fails, in which case the [`crate::InterpError`] can be recovered with
+    /// [`io::Error::into_inner`]. Output written before the failure is not rolled back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "name" => Some("world".to_string()),
+    ///     _ => None,
+    /// };
+    ///
+    /// let mut output = Vec::new();
+    /// text_interpolator
+    ///     .interp_stream("Hello 'name\n".as_bytes(), &mut output, &map)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(b"Hello world\n", output.as_slice());
+    /// ```
+    pub fn interp_stream<'m, R: Read, W: Write, S: Into<Substitution<'m>>>(
+        &mut self,
+        mut reader: R,
+        mut writer: W,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> io::Result<()> {
+        let map = ignore_args(map);
+        // Shared by every chunk, so limits and memoized substitutions span the whole input
+        let mut state = CallState::default();
+        let mut chunk = vec![0; CHUNK_SIZE];
+        // Bytes read but not yet interpolated, ending with an incomplete word or character
+        let mut pending: Vec<u8> = Vec::new();
+        let mut output = String::new();
+        // Bytes interpolated so far, which error spans are relative to
+        let mut offset = 0;
+
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            pending.extend_from_slice(&chunk[..read]);
+
+            let text = match std::str::from_utf8(&pending) {
+                Ok(text) => text,
+                // An incomplete character at the end is completed by the next read
+                Err(err) if err.error_len().is_none() => {
+                    std::str::from_utf8(&pending[..err.valid_up_to()]).map_err(invalid_data)?
+                }
+                Err(err) => return Err(invalid_data(err)),
+            };
+
+            // Text is cut where a complete word without a template starts, so runs of separators
+            // such as \r\n are never split, and neither is a conditional
+            let conditionals = self.conditional_ranges(text);
+            let complete = text
+                .char_indices()
+                .rev()
+                .filter(|&(index, ch)| {
+                    !(self.is_separator)(ch)
+                        && text[..index]
+                            .chars()
+                            .next_back()
+                            .is_some_and(|before| (self.is_separator)(before))
+                })
+                .map(|(index, _)| index)
+                .find(|&index| {
+                    let word = text[index..]
+                        .find(|ch| (self.is_separator)(ch))
+                        .map(|len| &text[index..index + len]);
+
+                    word.is_some_and(|word| !(self.is_template)(word))
+                        && !conditionals.iter().any(|range| range.contains(&index))
+                });
+
+            if let Some(complete) = complete {
+                self.interp_chunk(&text[..complete], &map, &mut state, &mut output)
+                    .map_err(|err| invalid_data(err.offset_span(offset)))?;
+                writer.write_all(output.as_bytes())?;
+
+                pending.drain(..complete);
+                offset += complete;
+            }
+        }
+
+        let text = std::str::from_utf8(&pending).map_err(invalid_data)?;
+
+        self.interp_chunk(text, &map, &mut state, &mut output)
+            .map_err(|err| invalid_data(err.offset_span(offset)))?;
+        writer.write_all(output.as_bytes())?;
+
+        writer.flush()
+    }
+
+    /// Interpolates a chunk of the input into output, replacing the output of the chunk before
+    /// it, which has already been written.
+    fn interp_chunk<'m, S: Into<Substitution<'m>>>(
+        &mut self,
+        text: &str,
+        map: &impl Fn(&str, &[&str]) -> Option<S>,
+        state: &mut CallState,
+        output: &mut String,
+    ) -> Result<(), InterpError> {
+        state.flushed += output.len();
+        output.clear();
+
+        self.with_hook(state, |text_interpolator, state| {
+            text_interpolator.interp_nested(text, map, state, output, None)
+        })
+    }
+}
+
+fn invalid_data(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reader that hands out at most a few bytes per read.
+    struct TrickleReader<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.step.min(buf.len()).min(self.bytes.len());
+            buf[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            self.step = self.step % 3 + 1;
+
+            Ok(len)
+        }
+    }
+
+    fn map_template(template: &str) -> Option<String> {
+        match template {
+            "noun" => Some("café".to_string()),
+            "sentence" => Some("A 'noun,  a 'verb.".to_string()),
+            "verb" => Some("naïve run".to_string()),
+            "blank" => Some(String::new()),
+            "premium" => Some("true".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn stream_matches_interp_across_chunk_boundaries() {
+        let mut interpolator = TextInterpolator::default();
+
+        for text in [
+            "Über 'sentence\n\n  'noun's 'missing\t'verb… ends 'noun",
+            "'noun\r\n'blank\r\n\r\n'noun 'blank\n\r\n 'verb\r\nends",
+            "Hello 'if(premium: dear friend) 'noun 'if(missing: old (very) old) friend",
+            "'if(premium: a 'if(premium: b c) d)\n'if(premium: unclosed 'noun e",
+            "line1 'blank\nline2 'blank 'blank\n'noun 'blank\n\n'blank x 'blank",
+            "a 'if(missing: x)\n'blank\r\nb 'if(missing: x) 'blank\n c\n'if(missing: x)",
+        ] {
+            let expected = interpolator.interp(text, &map_template).unwrap();
+
+            let mut output = Vec::new();
+            let reader = TrickleReader {
+                bytes: text.as_bytes(),
+                step: 1,
+            };
+            interpolator
+                .interp_stream(reader, &mut output, &map_template)
+                .unwrap();
+
+            assert_eq!(expected, String::from_utf8(output).unwrap());
+        }
+    }
+
+    #[test]
+    fn stream_limits_the_whole_output() {
+        let mut interpolator = TextInterpolator {
+            max_output_len: Some(20),
+            ..Default::default()
+        };
+        let text = "'noun a 'noun b 'noun c 'noun d";
+
+        assert!(matches!(
+            interpolator.interp(text, &map_template),
+            Err(crate::InterpError::OutputTooLarge { limit: 20 })
+        ));
+
+        let reader = TrickleReader {
+            bytes: text.as_bytes(),
+            step: 1,
+        };
+        let err = interpolator
+            .interp_stream(reader, &mut Vec::new(), &map_template)
+            .unwrap_err();
+
+        assert!(matches!(
+            *err.into_inner()
+                .unwrap()
+                .downcast::<crate::InterpError>()
+                .unwrap(),
+            crate::InterpError::OutputTooLarge { limit: 20 }
+        ));
+    }
+
+    #[test]
+    fn stream_rejects_invalid_utf8() {
+        let mut interpolator = TextInterpolator::default();
+        let mut output = Vec::new();
+
+        let result =
+            interpolator.interp_stream(&b"'noun \xff 'noun"[..], &mut output, &map_template);
+
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn stream_wraps_interp_errors() {
+        let mut interpolator = TextInterpolator::default();
+        let mut output = Vec::new();
+        let map = |template: &str| match template {
+            "loop" => Some("'loop"),
+            _ => None,
+        };
+
+        let err = interpolator
+            .interp_stream(&b"fine 'loop"[..], &mut output, &map)
+            .unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert!(err
+            .into_inner()
+            .unwrap()
+            .downcast::<crate::InterpError>()
+            .is_ok());
+    }
+}
@@ -0,0 +1,117 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// What a map returns for a template: the text to substitute, and whether to write it verbatim.
+///
+/// Maps rarely build one directly. Anything a map returns converts into one, whether it is a
+/// `String`, a borrowed `&str`, a `Cow<str>`, a [`Literal`], or a [`Template`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution<'m> {
+    pub text: Cow<'m, str>,
+    /// Whether the text is written exactly as it is, without expanding templates in it or
+    /// unescaping it, the same way as when [`TextInterpolator::recurse`] returns false.
+    ///
+    /// [`TextInterpolator::recurse`]: crate::TextInterpolator::recurse
+    pub literal: bool,
+    /// Whether templates in the text are expanded even when the interpolator treats
+    /// substitutions as literal by default, see
+    /// [`TextInterpolator::literal_by_default`].
+    ///
+    /// [`TextInterpolator::literal_by_default`]: crate::TextInterpolator::literal_by_default
+    pub template: bool,
+}
+
+/// Wraps a substitution that should be written verbatim, so markers in it, as in `it's 'quoted'`,
+/// are never taken for templates.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::{Literal, Substitution, TextInterpolator};
+///
+/// let mut text_interpolator = TextInterpolator::default();
+/// let map = |template: &str| match template {
+///     "greeting" => Some(Literal("Hello 'name").into()),
+///     "name" => Some(Substitution::from("Ada")),
+///     _ => None,
+/// };
+///
+/// let output = text_interpolator.interp("'greeting, 'name", &map).unwrap();
+///
+/// assert_eq!("Hello 'name, Ada", output);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Literal<S>(pub S);
+
+/// Wraps a substitution whose templates should be expanded even when the interpolator treats
+/// substitutions as literal data by default, marking it as trusted grammar rather than data.
+///
+/// # Examples
+///
+/// ```
+/// use text_interpolator::{Substitution, Template, TextInterpolator};
+///
+/// let mut text_interpolator = TextInterpolator {
+///     literal_by_default: true,
+///     ..Default::default()
+/// };
+/// let map = |template: &str| match template {
+///     "greeting" => Some(Template("Hello 'name").into()),
+///     "name" => Some(Substitution::from("'quoted' Ada")),
+///     _ => None,
+/// };
+///
+/// let output = text_interpolator.interp("'greeting", &map).unwrap();
+///
+/// assert_eq!("Hello 'quoted' Ada", output);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Template<S>(pub S);
+
+impl<'m> From<Cow<'m, str>> for Substitution<'m> {
+    fn from(text: Cow<'m, str>) -> Self {
+        Substitution {
+            text,
+            literal: false,
+            template: false,
+        }
+    }
+}
+
+impl<'m> From<&'m str> for Substitution<'m> {
+    fn from(text: &'m str) -> Self {
+        Cow::Borrowed(text).into()
+    }
+}
+
+impl<'m> From<&'m String> for Substitution<'m> {
+    fn from(text: &'m String) -> Self {
+        Cow::Borrowed(text.as_str()).into()
+    }
+}
+
+impl From<String> for Substitution<'_> {
+    fn from(text: String) -> Self {
+        Cow::<str>::Owned(text).into()
+    }
+}
+
+impl<'m, S: Into<Cow<'m, str>>> From<Literal<S>> for Substitution<'m> {
+    fn from(Literal(text): Literal<S>) -> Self {
+        Substitution {
+            text: text.into(),
+            literal: true,
+            template: false,
+        }
+    }
+}
+
+impl<'m, S: Into<Cow<'m, str>>> From<Template<S>> for Substitution<'m> {
+    fn from(Template(text): Template<S>) -> Self {
+        Substitution {
+            text: text.into(),
+            literal: false,
+            template: true,
+        }
+    }
+}
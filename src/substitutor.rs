@@ -0,0 +1,195 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::BuildHasher;
+
+use crate::{InterpError, TextInterpolator};
+
+/// A source of substitutions for templates.
+///
+/// Implemented for dictionaries, for map functions and for pairs of substitutors, where the
+/// second one is a fallback for templates the first one can't resolve.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use text_interpolator::{Substitutor, TextInterpolator};
+///
+/// struct Doubler;
+///
+/// impl Substitutor for Doubler {
+///     fn resolve(&self, template: &str) -> Option<String> {
+///         template.parse::<u32>().ok().map(|n| (n * 2).to_string())
+///     }
+/// }
+///
+/// let names = BTreeMap::from([("name".to_string(), "world".to_string())]);
+///
+/// let mut text_interpolator = TextInterpolator::default();
+/// let output = text_interpolator.interp_with("Hello 'name, '21", &(names, Doubler));
+///
+/// assert_eq!("Hello world, 42", output.unwrap());
+/// ```
+pub trait Substitutor {
+    /// Returns the substitute for template, or None if it has none.
+    fn resolve(&self, template: &str) -> Option<String>;
+}
+
+#[cfg(feature = "std")]
+impl<S: BuildHasher> Substitutor for HashMap<String, String, S> {
+    fn resolve(&self, template: &str) -> Option<String> {
+        self.get(template).cloned()
+    }
+}
+
+impl Substitutor for BTreeMap<String, String> {
+    fn resolve(&self, template: &str) -> Option<String> {
+        self.get(template).cloned()
+    }
+}
+
+impl<F: Fn(&str) -> Option<String>> Substitutor for F {
+    fn resolve(&self, template: &str) -> Option<String> {
+        self(template)
+    }
+}
+
+impl<A: Substitutor, B: Substitutor> Substitutor for (A, B) {
+    fn resolve(&self, template: &str) -> Option<String> {
+        self.0
+            .resolve(template)
+            .or_else(|| self.1.resolve(template))
+    }
+}
+
+/// Tries each substitutor in order, returning the first substitute found.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use text_interpolator::{Chain, TextInterpolator};
+///
+/// let user = BTreeMap::from([("name".to_string(), "Ada".to_string())]);
+/// let global = BTreeMap::from([
+///     ("name".to_string(), "stranger".to_string()),
+///     ("greeting".to_string(), "Hi".to_string()),
+/// ]);
+/// let builtin = |template: &str| Some(format!("<{template}>"));
+///
+/// let mut text_interpolator = TextInterpolator::default();
+/// let output = text_interpolator
+///     .interp_with("'greeting 'name 'missing", &Chain(vec![&user, &global, &builtin]));
+///
+/// assert_eq!("Hi Ada <missing>", output.unwrap());
+/// ```
+pub struct Chain<'a>(pub Vec<&'a dyn Substitutor>);
+
+impl Substitutor for Chain<'_> {
+    fn resolve(&self, template: &str) -> Option<String> {
+        self.0
+            .iter()
+            .find_map(|substitutor| substitutor.resolve(template))
+    }
+}
+
+impl TextInterpolator {
+    /// Interpolates text using a [`Substitutor`] instead of a map function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let mut text_interpolator = TextInterpolator::default();
+    /// let map = BTreeMap::from([
+    ///     ("greeting".to_string(), "Hello 'name".to_string()),
+    ///     ("name".to_string(), "world".to_string()),
+    /// ]);
+    ///
+    /// let output = text_interpolator.interp_with("'greeting!", &map).unwrap();
+    ///
+    /// assert_eq!("Hello world!", output);
+    /// ```
+    pub fn interp_with(
+        &mut self,
+        text: &str,
+        substitutor: &impl Substitutor,
+    ) -> Result<String, InterpError> {
+        self.interp(text, &|template| substitutor.resolve(template))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_template(template: &str) -> Option<String> {
+        match template {
+            "sentence" => Some("A 'adj 'noun.".to_string()),
+            "noun" => Some("place".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interp_with_closure_matches_interp() {
+        let mut text_interpolator = TextInterpolator::default();
+
+        assert_eq!(
+            text_interpolator
+                .interp("'sentence 'missing", &map_template)
+                .unwrap(),
+            text_interpolator
+                .interp_with("'sentence 'missing", &map_template)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn chain_returns_first_substitute() {
+        let mut text_interpolator = TextInterpolator::default();
+        let first = BTreeMap::from([("noun".to_string(), "house".to_string())]);
+        let second = BTreeMap::from([
+            ("noun".to_string(), "tent".to_string()),
+            ("adj".to_string(), "big".to_string()),
+        ]);
+
+        assert_eq!(
+            "A big house. 'missing",
+            text_interpolator
+                .interp_with(
+                    "'sentence 'missing",
+                    &Chain(vec![&first, &second, &map_template])
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            "'sentence",
+            text_interpolator
+                .interp_with("'sentence", &Chain(Vec::new()))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn fallback_pair_prefers_first_substitutor() {
+        let mut text_interpolator = TextInterpolator::default();
+        let overrides = BTreeMap::from([
+            ("noun".to_string(), "house".to_string()),
+            ("adj".to_string(), "big".to_string()),
+        ]);
+
+        assert_eq!(
+            "A big house. 'missing",
+            text_interpolator
+                .interp_with("'sentence 'missing", &(overrides, map_template))
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{TemplateSplit, TextInterpolator};
+
+/// A piece of text as split by [`TextInterpolator::tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// A run of separators, or a word without a template, which still has to be unescaped.
+    Literal(&'a str),
+    /// A word containing a template. The suffix may itself start with another template, as in
+    /// `'greeting'punctuation`, and anything parsed from between the name and the suffix, such
+    /// as a default or a count, is left out.
+    Template {
+        prefix: &'a str,
+        name: &'a str,
+        suffix: &'a str,
+    },
+    /// A word whose template is deferred with `'!name`, which interp writes as prefix followed
+    /// by suffix, leaving the template for a later pass.
+    Deferred { prefix: &'a str, suffix: &'a str },
+    /// A whole `'if(condition: body)`, whose body is written in its place when the template
+    /// named by condition holds, see [`TextInterpolator::interp`].
+    Conditional { condition: &'a str, body: &'a str },
+}
+
+impl TextInterpolator {
+    /// Lazily splits text into literal pieces and templates without substituting anything, so
+    /// callers can do their own substitution and reassembly.
+    ///
+    /// Words are split and templates extracted the same way [`TextInterpolator::interp`] does
+    /// it, and the templates and conditions are those [`TextInterpolator::find_templates`]
+    /// lists, with the templates of a conditional's body left in the body. Joining the
+    /// literals with the prefix, substitute, and suffix of each template, the prefix and suffix
+    /// of each deferred word, and the reassembled body of each conditional that holds rebuilds
+    /// the interpolated text, except that a conditional that doesn't hold also takes one
+    /// neighboring run of separators with it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{TextInterpolator, Token};
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    ///
+    /// let tokens: Vec<Token> = text_interpolator.tokens("Hello ('name)!").collect();
+    ///
+    /// assert_eq!(
+    ///     vec![
+    ///         Token::Literal("Hello"),
+    ///         Token::Literal(" "),
+    ///         Token::Template { prefix: "(", name: "name", suffix: ")!" },
+    ///     ],
+    ///     tokens
+    /// );
+    ///
+    /// let tokens: Vec<Token> = text_interpolator.tokens("'if(premium: Thanks!)").collect();
+    /// assert_eq!(vec![Token::Conditional { condition: "premium", body: "Thanks!" }], tokens);
+    /// ```
+    pub fn tokens<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Token<'a>> {
+        let mut conditional = self.find_conditional(text, 0);
+        let end = conditional
+            .as_ref()
+            .map_or(text.len(), |conditional| conditional.range.start);
+        let mut pieces = self.split_words(&text[..end]);
+
+        core::iter::from_fn(move || {
+            if let Some((is_word, item)) = pieces.next() {
+                return Some(self.word_token(is_word, item));
+            }
+
+            // The words up to the conditional are done, so it comes next, then the words after it
+            let found = conditional.take()?;
+            conditional = self.find_conditional(text, found.range.end);
+            let end = conditional
+                .as_ref()
+                .map_or(text.len(), |conditional| conditional.range.start);
+            pieces = self.split_words(&text[found.range.end..end]);
+
+            Some(Token::Conditional {
+                condition: &text[found.condition],
+                body: &text[found.body],
+            })
+        })
+    }
+
+    /// The token of a single piece of text outside conditionals.
+    fn word_token<'a>(&self, is_word: bool, item: &'a str) -> Token<'a> {
+        if !is_word {
+            return Token::Literal(item);
+        }
+
+        let template_split = (self.extract_template)(item);
+        if template_split.deferred {
+            return Token::Deferred {
+                prefix: template_split.prefix,
+                suffix: template_split.suffix,
+            };
+        }
+        if template_split.template.is_empty() {
+            return Token::Literal(item);
+        }
+
+        Token::Template {
+            prefix: template_split.prefix,
+            name: template_split.template,
+            suffix: template_split.suffix,
+        }
+    }
+
+    /// Lazily extracts the template of every word in text, one split per word, without
+    /// substituting anything, so the structure of a whole document can be inspected.
+    ///
+    /// Words are split on the interpolator's separators and passed to its extract_template, the
+    /// same way [`TextInterpolator::interp`] does it. Words without a template yield a split
+    /// whose template is empty, and escaped words are yielded as they are written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    ///
+    /// let splits: Vec<_> = text_interpolator
+    ///     .split_all("A ('noun|thing), 'cat#lives")
+    ///     .collect();
+    ///
+    /// assert_eq!(3, splits.len());
+    /// assert_eq!("", splits[0].template);
+    /// assert_eq!(("(", "noun", "),"), (splits[1].prefix, splits[1].template, splits[1].suffix));
+    /// assert_eq!(Some("thing"), splits[1].default);
+    /// assert_eq!(Some("lives"), splits[2].count);
+    /// ```
+    pub fn split_all<'a>(&'a self, text: &'a str) -> impl Iterator<Item = TemplateSplit<'a>> {
+        self.split_words(text)
+            .filter(|&(is_word, _)| is_word)
+            .map(|(_, word)| (self.extract_template)(word))
+    }
+
+    /// Lists the distinct template names found in text, in the order they first appear, without
+    /// performing any substitution.
+    ///
+    /// Words are split into templates the same way [`TextInterpolator::interp`] does it, but
+    /// only the literal input is scanned since the contents of substitutions aren't known yet.
+    /// The condition of a conditional is listed as a template, followed by the templates of its
+    /// body, which are used whenever the condition holds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    ///
+    /// let templates = text_interpolator.find_templates("A 'adj 'noun, another 'noun.");
+    /// assert_eq!(vec!["adj", "noun"], templates);
+    ///
+    /// let templates = text_interpolator.find_templates("'if(premium: Thanks, 'name!) 'noun");
+    /// assert_eq!(vec!["premium", "name", "noun"], templates);
+    /// ```
+    pub fn find_templates(&self, text: &str) -> Vec<String> {
+        let mut templates: Vec<String> = Vec::new();
+
+        for (name, _) in self.template_words(text) {
+            if !templates.iter().any(|template| template == name) {
+                templates.push(name.to_string());
+            }
+        }
+
+        templates
+    }
+
+    /// Returns the byte range of every template word in text, in order, so callers can
+    /// highlight them in the original string.
+    ///
+    /// A range covers the whole word, including any prefix or suffix around the template. The
+    /// condition of a conditional gets a range covering only its name, and the template words of
+    /// its body follow, the same way [`TextInterpolator::find_templates`] lists them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    /// let text = "A 'noun and  'verb.";
+    ///
+    /// let spans = text_interpolator.template_spans(text);
+    ///
+    /// assert_eq!(vec![2..7, 13..19], spans);
+    /// assert_eq!("'verb.", &text[spans[1].clone()]);
+    /// ```
+    pub fn template_spans(&self, text: &str) -> Vec<Range<usize>> {
+        self.template_words(text)
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_cover_every_piece_of_text() {
+        let text_interpolator = TextInterpolator::default();
+
+        let tokens: Vec<Token> = text_interpolator
+            .tokens("''tis  'noun's 'a'b it's:'adj|x\t")
+            .collect();
+
+        assert_eq!(
+            vec![
+                Token::Literal("''tis"),
+                Token::Literal("  "),
+                Token::Template {
+                    prefix: "",
+                    name: "noun",
+                    suffix: "'s"
+                },
+                Token::Literal(" "),
+                Token::Template {
+                    prefix: "",
+                    name: "a",
+                    suffix: "'b"
+                },
+                Token::Literal(" "),
+                Token::Template {
+                    prefix: "it's:",
+                    name: "adj",
+                    suffix: ""
+                },
+                Token::Literal("\t"),
+            ],
+            tokens
+        );
+        assert_eq!(None, text_interpolator.tokens("").next());
+    }
+
+    #[test]
+    fn split_all_extracts_every_word() {
+        let text_interpolator = TextInterpolator::default();
+        let text = " ''tis\t'a(1,2)'b  it's:'!later ";
+
+        let splits: Vec<TemplateSplit> = text_interpolator.split_all(text).collect();
+
+        assert_eq!(3, splits.len());
+        assert_eq!(
+            ("", "", ""),
+            (splits[0].prefix, splits[0].template, splits[0].suffix)
+        );
+        assert_eq!(
+            ("a", Some("1,2"), "'b"),
+            (splits[1].template, splits[1].args, splits[1].suffix)
+        );
+        assert!(splits[2].deferred);
+        assert_eq!(("it's:'", "later"), (splits[2].prefix, splits[2].suffix));
+        assert_eq!(
+            text_interpolator.find_templates(text),
+            text_interpolator
+                .split_all(text)
+                .map(|split| split.template)
+                .filter(|template| !template.is_empty())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(0, text_interpolator.split_all(" \n ").count());
+    }
+
+    #[test]
+    fn tokens_reassemble_into_interpolated_text() {
+        let mut text_interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "adj" => Some("funny"),
+            "noun" => Some("place"),
+            _ => None,
+        };
+        let text = "A ('adj) 'noun, the 'noun's door.";
+
+        let reassembled: String = text_interpolator
+            .tokens(text)
+            .map(|token| match token {
+                Token::Literal(literal) => literal.to_string(),
+                Token::Template {
+                    prefix,
+                    name,
+                    suffix,
+                } => [prefix, map(name).unwrap(), suffix].concat(),
+                token => unreachable!("{token:?}"),
+            })
+            .collect();
+
+        assert_eq!(text_interpolator.interp(text, &map).unwrap(), reassembled);
+    }
+
+    #[test]
+    fn tokens_yield_conditionals_and_deferred_words() {
+        let text_interpolator = TextInterpolator::default();
+        let map = |template: &str| match template {
+            "premium" => Some("yes"),
+            "name" => Some("Ada"),
+            _ => None,
+        };
+        let text = "Hi 'if(premium: dear 'name,) see ('!later) 'if(trial: x) 'name";
+
+        let tokens: Vec<Token> = text_interpolator.tokens(text).collect();
+
+        assert_eq!(
+            vec![
+                Token::Literal("Hi"),
+                Token::Literal(" "),
+                Token::Conditional {
+                    condition: "premium",
+                    body: "dear 'name,"
+                },
+                Token::Literal(" "),
+                Token::Literal("see"),
+                Token::Literal(" "),
+                Token::Deferred {
+                    prefix: "('",
+                    suffix: "later)"
+                },
+                Token::Literal(" "),
+                Token::Conditional {
+                    condition: "trial",
+                    body: "x"
+                },
+                Token::Literal(" "),
+                Token::Template {
+                    prefix: "",
+                    name: "name",
+                    suffix: ""
+                },
+            ],
+            tokens
+        );
+        assert_eq!(
+            vec!["premium", "name", "trial"],
+            text_interpolator.find_templates(text)
+        );
+
+        let reassembled: String = tokens
+            .iter()
+            .map(|token| match *token {
+                Token::Literal(literal) => literal.to_string(),
+                Token::Template {
+                    prefix,
+                    name,
+                    suffix,
+                } => [prefix, map(name).unwrap(), suffix].concat(),
+                Token::Deferred { prefix, suffix } => [prefix, suffix].concat(),
+                Token::Conditional { condition, body } => match map(condition) {
+                    Some(_) => text_interpolator.interp_ref(body, &map).unwrap(),
+                    None => String::new(),
+                },
+            })
+            .collect();
+
+        // The conditional that doesn't hold takes one of the spaces around it along
+        assert_eq!(
+            text_interpolator.interp_ref(text, &map).unwrap(),
+            reassembled.replacen("  ", " ", 1)
+        );
+    }
+
+    #[test]
+    fn conditions_and_bodies_are_templates() {
+        let text_interpolator = TextInterpolator::default();
+        let text = "Hi 'if(premium: dear 'name,) 'if( trial : 'if(banned: x)) 'noun 'if(x)";
+
+        assert_eq!(
+            vec!["premium", "name", "trial", "banned", "noun", "if"],
+            text_interpolator.find_templates(text)
+        );
+        assert_eq!(
+            vec!["premium", "'name,", "trial", "banned", "'noun", "'if(x)"],
+            text_interpolator
+                .template_spans(text)
+                .into_iter()
+                .map(|span| &text[span])
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,561 @@
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use core::cell::RefCell;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+
+use crate::{
+    ignore_args, CallState, InterpError, NestedTemplateLoopError, Resolved, Substitution,
+    TextInterpolator,
+};
+
+/// A problem found by [`TextInterpolator::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// A template, or the count of a counted template, has no substitution.
+    UndefinedTemplate(String),
+    /// A template is not registered while unknown templates are rejected.
+    UnknownTemplate(String),
+    NestedTemplateLoop(NestedTemplateLoopError),
+    /// Substitutions nest deeper than the interpolator's max depth.
+    MaxDepthExceeded {
+        depth: usize,
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::UndefinedTemplate(template) => {
+                write!(f, "template '{template}' has no substitution")
+            }
+            ValidationIssue::UnknownTemplate(template) => {
+                write!(f, "template '{template}' is not a known template")
+            }
+            ValidationIssue::NestedTemplateLoop(err) => err.fmt(f),
+            ValidationIssue::MaxDepthExceeded { depth } => {
+                write!(
+                    f,
+                    "nested templates exceeded the maximum depth at depth {depth}"
+                )
+            }
+        }
+    }
+}
+
+/// A substitution whose words are still being checked.
+struct Pending<'m> {
+    text: Cow<'m, str>,
+    pos: usize,
+    /// Name of the template being substituted, or None for the input text.
+    name: Option<String>,
+    /// Whether the substitution came from the map, so its template only needs checking once.
+    from_map: bool,
+    /// Depth of substitutions found in the text.
+    depth: usize,
+    /// Whether the text is the rest of a word after a template, where only templates that
+    /// resolve are substituted.
+    adjacent: bool,
+}
+
+impl TextInterpolator {
+    /// Checks that every template in text resolves, including those inside substitutions,
+    /// without building any output.
+    ///
+    /// Substitutions are walked the same way [`TextInterpolator::interp`] expands them, but
+    /// instead of stopping at the first problem every undefined template, loop, and excessive
+    /// nesting is reported, each only once. Templates substituted from the map are checked the
+    /// first time they are found, so widely shared templates don't slow validation down.
+    ///
+    /// Conditionals are evaluated with the map the same way interp evaluates them, so only the
+    /// bodies of conditions that hold are checked, and a condition without a substitution is
+    /// never an issue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text_interpolator::{TextInterpolator, ValidationIssue};
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'name"),
+    ///     "ping" => Some("'pong"),
+    ///     "pong" => Some("'ping"),
+    ///     _ => None,
+    /// };
+    ///
+    /// assert!(text_interpolator.validate("'greeting|hi", &|_| None::<&str>).is_ok());
+    ///
+    /// let issues = text_interpolator.validate("'greeting 'ping", &map).unwrap_err();
+    ///
+    /// assert_eq!(ValidationIssue::UndefinedTemplate("name".to_string()), issues[0]);
+    /// assert!(matches!(
+    ///     &issues[1],
+    ///     ValidationIssue::NestedTemplateLoop(err) if err.chain == ["ping", "pong", "ping"]
+    /// ));
+    /// ```
+    pub fn validate<'m, S: Into<Substitution<'m>>>(
+        &self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> Result<(), Vec<ValidationIssue>> {
+        self.validate_with(text, map, &Cell::new(false))
+    }
+
+    /// Validates text the same way as [`TextInterpolator::validate`], setting in_suffix while
+    /// map is asked for a template that is only literal text if it doesn't resolve, such as
+    /// the `s` of `'noun's`.
+    fn validate_with<'m, S: Into<Substitution<'m>>>(
+        &self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+        in_suffix: &Cell<bool>,
+    ) -> Result<(), Vec<ValidationIssue>> {
+        let mut issues = Vec::new();
+        let mut checked = BTreeSet::new();
+        let mut chain: Vec<String> = Vec::new();
+
+        // Text whose conditions loop through aliases or are unknown fails as a whole, so it
+        // isn't walked
+        let state = CallState::default();
+        let expand = |text, depth, issues: &mut Vec<ValidationIssue>| match self
+            .expand_conditionals(text, &ignore_args(map), &state, depth)
+        {
+            Ok(text) => text,
+            Err(err) => {
+                let issue = match err {
+                    InterpError::UnknownTemplate { template, .. } => {
+                        ValidationIssue::UnknownTemplate(template)
+                    }
+                    InterpError::NestedTemplateLoop(err) => {
+                        ValidationIssue::NestedTemplateLoop(err)
+                    }
+                    err => {
+                        unreachable!("conditions fail with loops or unknown templates, not {err}")
+                    }
+                };
+                report(issues, issue);
+                Cow::Borrowed("")
+            }
+        };
+
+        let mut stack = vec![Pending {
+            text: expand(Cow::Borrowed(text), 1, &mut issues),
+            pos: 0,
+            name: None,
+            from_map: false,
+            depth: 1,
+            adjacent: false,
+        }];
+
+        while let Some(pending) = stack.last_mut() {
+            let depth = pending.depth;
+            let adjacent = pending.adjacent;
+
+            let start = pending.pos;
+            let Some((is_word, len)) = self
+                .split_words(&pending.text[start..])
+                .next()
+                .map(|(is_word, piece)| (is_word, piece.len()))
+            else {
+                let pending = stack.pop().expect("stack is not empty");
+                if let Some(name) = pending.name {
+                    chain.pop();
+                    if pending.from_map {
+                        checked.insert(name);
+                    }
+                }
+                continue;
+            };
+
+            pending.pos += len;
+            if !is_word {
+                continue;
+            }
+
+            let piece = &pending.text[start..start + len];
+            let template_split = (self.extract_template)(piece);
+            if template_split.template.is_empty() {
+                continue;
+            }
+
+            if self.reject_unknown
+                && self.max_depth > 0
+                && !self.is_known_template(template_split.template)
+            {
+                if !adjacent || self.delimited {
+                    report(
+                        &mut issues,
+                        ValidationIssue::UnknownTemplate(template_split.template.to_string()),
+                    );
+                }
+                continue;
+            }
+
+            let Resolved {
+                substitute,
+                name,
+                from_map,
+                literal,
+                ..
+            } = {
+                in_suffix.set(adjacent && !self.delimited);
+                let resolved = self.resolve(&template_split, &ignore_args(map), depth);
+                in_suffix.set(false);
+
+                match resolved {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        report(&mut issues, ValidationIssue::NestedTemplateLoop(err));
+                        continue;
+                    }
+                }
+            };
+            let name = name.into_owned();
+
+            if adjacent && substitute.is_none() && !self.delimited {
+                continue;
+            }
+
+            if let Some(count) = template_split.count {
+                if map(count).is_none() && count.parse::<f64>().is_err() {
+                    report(
+                        &mut issues,
+                        ValidationIssue::UndefinedTemplate(count.to_string()),
+                    );
+                }
+            }
+
+            if substitute.is_none() && self.max_depth > 0 {
+                report(
+                    &mut issues,
+                    ValidationIssue::UndefinedTemplate(template_split.template.to_string()),
+                );
+            }
+
+            // The rest of the word is checked once this template is, and past templates that
+            // don't resolve only if they are delimited
+            if !template_split.suffix.is_empty() && (substitute.is_some() || self.delimited) {
+                let suffix = template_split.suffix.to_string();
+                stack.push(Pending {
+                    text: Cow::Owned(suffix),
+                    pos: 0,
+                    name: None,
+                    from_map: false,
+                    depth,
+                    adjacent: true,
+                });
+            }
+
+            let Some(substitute) = substitute else {
+                continue;
+            };
+
+            if depth > self.max_depth {
+                report(&mut issues, ValidationIssue::MaxDepthExceeded { depth });
+                continue;
+            }
+
+            let loop_start = match self.detect_loops {
+                true => chain.iter().position(|entered| *entered == name),
+                false => None,
+            };
+            if let Some(start) = loop_start {
+                let mut loop_chain = chain[start..].to_vec();
+                loop_chain.push(name);
+                report(
+                    &mut issues,
+                    ValidationIssue::NestedTemplateLoop(NestedTemplateLoopError {
+                        chain: loop_chain,
+                        depth,
+                    }),
+                );
+                continue;
+            }
+
+            if (from_map && checked.contains(&name))
+                || literal
+                || !self.recursive
+                || !(self.recurse)(&name)
+            {
+                continue;
+            }
+
+            chain.push(name.clone());
+            stack.push(Pending {
+                text: expand(substitute, depth, &mut issues),
+                pos: 0,
+                name: Some(name),
+                from_map,
+                depth: depth + 1,
+                adjacent: false,
+            });
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+impl TextInterpolator {
+    /// Returns the name of every template looked up while expanding text, including those
+    /// inside substitutions and those the map has no entry for, so unused dictionary entries
+    /// can be pruned and missing ones found.
+    ///
+    /// Text is walked the same way as [`TextInterpolator::validate`], so expansion doesn't
+    /// stop at loops or undefined templates. A name written with a case directive, such as
+    /// `'Noun`, is left out when its lowercase form was looked up as well, and so are numeric
+    /// counts and unresolved text after a template, such as the `s` of `'noun's`. The condition
+    /// of a conditional is a key, while the templates of its body are only looked up when the
+    /// condition holds.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use text_interpolator::TextInterpolator;
+    ///
+    /// let text_interpolator = TextInterpolator::default();
+    /// let map = |template: &str| match template {
+    ///     "greeting" => Some("Hello 'Name"),
+    ///     "name" => Some("world"),
+    ///     "unused" => Some("never"),
+    ///     _ => None,
+    /// };
+    ///
+    /// let keys = text_interpolator.required_keys("'greeting, 'missing!", &map);
+    /// let expected = HashSet::from(["greeting", "name", "missing"].map(String::from));
+    ///
+    /// assert_eq!(expected, keys);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn required_keys<'m, S: Into<Substitution<'m>>>(
+        &self,
+        text: &str,
+        map: &impl Fn(&str) -> Option<S>,
+    ) -> HashSet<String> {
+        let found = RefCell::new(HashSet::new());
+        let missing = RefCell::new(HashSet::new());
+        let in_suffix = Cell::new(false);
+
+        let recording = |template: &str| {
+            let substitute = map(template);
+            if !template.is_empty() {
+                match substitute {
+                    Some(_) => found.borrow_mut().insert(template.to_string()),
+                    // Such as the s of a possessive, which is written as it is
+                    None if in_suffix.get() => false,
+                    None => missing.borrow_mut().insert(template.to_string()),
+                };
+            }
+            substitute
+        };
+
+        // Issues are found again through the missing lookups
+        let _ = self.validate_with(text, &recording, &in_suffix);
+
+        let mut keys = found.into_inner();
+        let missing = missing.into_inner();
+        for template in &missing {
+            let lowercase = template.to_lowercase();
+            let case_fallback = self.case_directives
+                && lowercase != *template
+                && (keys.contains(&lowercase) || missing.contains(&lowercase));
+            if !case_fallback && template.parse::<f64>().is_err() {
+                keys.insert(template.clone());
+            }
+        }
+
+        keys
+    }
+}
+
+/// Adds an issue unless the same one has already been reported.
+fn report(issues: &mut Vec<ValidationIssue>, issue: ValidationIssue) {
+    if !issues.contains(&issue) {
+        issues.push(issue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_template(template: &str) -> Option<&'static str> {
+        match template {
+            "paragraph" => Some("'sentence 'sentence 'missing"),
+            "sentence" => Some("A 'adj 'noun#count, 'Noun 'thing|'other."),
+            "adj" => Some("funny"),
+            "noun" => Some("place"),
+            "a" => Some("'b"),
+            "b" => Some("'c 'a"),
+            "c" => Some("'Missing"),
+            "adjacent" => Some("'adj'noun's'missing"),
+            "deep" => Some("'deeper"),
+            "deeper" => Some("'noun"),
+            "premium" => Some("yes"),
+            "loop" => Some("'if(premium: 'loop)"),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn validate_accepts_resolvable_text() {
+        let text_interpolator = TextInterpolator::default();
+
+        assert_eq!(
+            Ok(()),
+            text_interpolator.validate(
+                "'adj 'noun#3 'Noun 'missing|x ''escaped 'adjacent",
+                &map_template
+            )
+        );
+    }
+
+    #[test]
+    fn validate_reports_each_issue_once() {
+        let text_interpolator = TextInterpolator::default();
+
+        let issues = text_interpolator
+            .validate("'paragraph 'a 'missing 'sentence", &map_template)
+            .unwrap_err();
+
+        assert_eq!(
+            vec![
+                ValidationIssue::UndefinedTemplate("count".to_string()),
+                ValidationIssue::UndefinedTemplate("other".to_string()),
+                ValidationIssue::UndefinedTemplate("missing".to_string()),
+                ValidationIssue::UndefinedTemplate("Missing".to_string()),
+                ValidationIssue::NestedTemplateLoop(NestedTemplateLoopError {
+                    chain: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+                    depth: 3,
+                }),
+            ],
+            issues
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn required_keys_include_nested_and_missing_templates() {
+        let text_interpolator = TextInterpolator::default();
+
+        let keys = text_interpolator.required_keys("'paragraph 'a 'deep#2", &map_template);
+
+        let mut keys: Vec<String> = keys.into_iter().collect();
+        keys.sort();
+        assert_eq!(
+            vec![
+                "a",
+                "adj",
+                "b",
+                "c",
+                "count",
+                "deep",
+                "deeper",
+                "missing",
+                "noun",
+                "other",
+                "paragraph",
+                "sentence",
+                "thing",
+            ],
+            keys
+        );
+        assert!(text_interpolator
+            .required_keys("no templates", &map_template)
+            .is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn required_keys_leave_out_possessives() {
+        let mut text_interpolator = TextInterpolator::default();
+        let keys = |text_interpolator: &TextInterpolator, text| {
+            let mut keys: Vec<String> = text_interpolator
+                .required_keys(text, &map_template)
+                .into_iter()
+                .collect();
+            keys.sort();
+            keys
+        };
+
+        assert_eq!(vec!["noun"], keys(&text_interpolator, "'noun's"));
+        assert_eq!(Ok(()), text_interpolator.validate("'noun's", &map_template));
+        assert_eq!(
+            vec!["missing", "noun"],
+            keys(&text_interpolator, "'missing's 'noun's")
+        );
+
+        text_interpolator.strict = true;
+        assert_eq!(
+            "place's",
+            text_interpolator.interp("'noun's", &map_template).unwrap()
+        );
+    }
+
+    #[test]
+    fn validate_only_checks_bodies_of_conditions_that_hold() {
+        let text_interpolator = TextInterpolator::default();
+
+        assert_eq!(
+            Ok(()),
+            text_interpolator.validate("'if(missing: 'loop 'undefined) ok", &map_template)
+        );
+        assert_eq!(
+            Err(vec![
+                ValidationIssue::UndefinedTemplate("undefined".to_string()),
+                ValidationIssue::NestedTemplateLoop(NestedTemplateLoopError {
+                    chain: vec!["loop".to_string(), "loop".to_string()],
+                    depth: 2,
+                }),
+            ]),
+            text_interpolator.validate("'if(premium: 'undefined 'loop)", &map_template)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn required_keys_include_conditions() {
+        let text_interpolator = TextInterpolator::default();
+
+        let keys = text_interpolator.required_keys(
+            "'if(premium: 'adj 'if(missing: 'noun)) 'if(blank: 'thing)",
+            &map_template,
+        );
+
+        let mut keys: Vec<String> = keys.into_iter().collect();
+        keys.sort();
+        assert_eq!(vec!["adj", "blank", "missing", "premium"], keys);
+    }
+
+    #[test]
+    fn validate_reports_max_depth() {
+        let mut text_interpolator = TextInterpolator {
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Err(vec![ValidationIssue::MaxDepthExceeded { depth: 3 }]),
+            text_interpolator.validate("'deep", &map_template)
+        );
+
+        text_interpolator.max_depth = 0;
+        assert_eq!(
+            Ok(()),
+            text_interpolator.validate("'missing", &map_template)
+        );
+    }
+}